@@ -0,0 +1,141 @@
+use {
+    crate::{
+        input::{
+            AbsInfo
+        },
+        input_sys::{
+            AbsoluteAxis
+        }
+    }
+};
+
+/// Observed behavior of a single absolute axis, used to propose corrected axis parameters.
+///
+/// The axis is assumed to start at rest; any jitter seen before it's moved
+/// away for the first time is treated as noise around its center.
+#[derive(Clone, Debug)]
+pub struct AbsoluteAxisCalibration {
+    axis: AbsoluteAxis,
+    current: AbsInfo,
+    rest: i32,
+    rest_threshold: i32,
+    rest_deviation: i32,
+    rest_sample_count: usize,
+    has_left_rest: bool,
+    minimum: i32,
+    maximum: i32
+}
+
+impl AbsoluteAxisCalibration {
+    pub fn new( axis: AbsoluteAxis, current: AbsInfo ) -> Self {
+        let range = current.maximum as i64 - current.minimum as i64;
+        let rest_threshold = std::cmp::max( range / 8, 1 ) as i32;
+
+        AbsoluteAxisCalibration {
+            axis,
+            rest: current.value,
+            rest_threshold,
+            rest_deviation: 0,
+            rest_sample_count: 0,
+            has_left_rest: false,
+            minimum: current.value,
+            maximum: current.value,
+            current
+        }
+    }
+
+    pub fn axis( &self ) -> AbsoluteAxis {
+        self.axis
+    }
+
+    /// The smallest value seen so far.
+    pub fn observed_minimum( &self ) -> i32 {
+        self.minimum
+    }
+
+    /// The biggest value seen so far.
+    pub fn observed_maximum( &self ) -> i32 {
+        self.maximum
+    }
+
+    pub fn observe( &mut self, position: i32 ) {
+        self.minimum = std::cmp::min( self.minimum, position );
+        self.maximum = std::cmp::max( self.maximum, position );
+
+        if self.has_left_rest {
+            return;
+        }
+
+        let deviation = (position as i64 - self.rest as i64).abs();
+        if deviation > self.rest_threshold as i64 {
+            self.has_left_rest = true;
+        } else {
+            self.rest_deviation = std::cmp::max( self.rest_deviation, deviation as i32 );
+            self.rest_sample_count += 1;
+        }
+    }
+
+    /// Proposes new parameters for the axis based on what was observed.
+    ///
+    /// The range is only replaced if the axis was actually moved,
+    /// and the deadzone is only replaced if the axis was seen at rest.
+    pub fn proposal( &self ) -> AbsInfo {
+        let mut info = self.current.clone();
+        if self.has_left_rest {
+            info.minimum = self.minimum;
+            info.maximum = self.maximum;
+            info.value = std::cmp::min( std::cmp::max( info.value, info.minimum ), info.maximum );
+        }
+
+        if self.rest_sample_count > 0 {
+            info.deadzone = self.rest_deviation;
+        }
+
+        info
+    }
+}
+
+#[cfg(test)]
+fn test_info() -> AbsInfo {
+    AbsInfo {
+        value: 128,
+        minimum: 0,
+        maximum: 255,
+        noise_threshold: 0,
+        deadzone: 15,
+        resolution: 0
+    }
+}
+
+#[test]
+fn test_calibration_without_movement_keeps_current_info() {
+    let calibration = AbsoluteAxisCalibration::new( AbsoluteAxis::X, test_info() );
+    assert_eq!( calibration.proposal(), test_info() );
+}
+
+#[test]
+fn test_calibration_proposes_observed_range_and_deadzone() {
+    let mut calibration = AbsoluteAxisCalibration::new( AbsoluteAxis::X, test_info() );
+    for &position in &[129, 126, 131, 128, 60, 12, 80, 200, 243, 128] {
+        calibration.observe( position );
+    }
+
+    let proposal = calibration.proposal();
+    assert_eq!( proposal.minimum, 12 );
+    assert_eq!( proposal.maximum, 243 );
+    assert_eq!( proposal.deadzone, 3 );
+    assert_eq!( proposal.value, 128 );
+}
+
+#[test]
+fn test_calibration_ignores_center_after_movement() {
+    let mut calibration = AbsoluteAxisCalibration::new( AbsoluteAxis::X, test_info() );
+    for &position in &[0, 100, 170, 255] {
+        calibration.observe( position );
+    }
+
+    let proposal = calibration.proposal();
+    assert_eq!( proposal.minimum, 0 );
+    assert_eq!( proposal.maximum, 255 );
+    assert_eq!( proposal.deadzone, 15 );
+}
@@ -0,0 +1,200 @@
+use {
+    std::{
+        fmt,
+        io
+    },
+    crate::{
+        uinput::{
+            DeviceCreateError
+        }
+    }
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    /// The process doesn't have the permissions required to access the device.
+    PermissionDenied,
+    /// The device was unplugged or doesn't exist.
+    DeviceGone,
+    /// The operation isn't supported by the device or by the kernel.
+    NotSupported,
+    /// The device is grabbed for exclusive access by someone else.
+    Busy,
+    /// Access to the device was revoked.
+    Revoked,
+    InvalidArgument,
+    TimedOut,
+    Other
+}
+
+/// The error type used throughout the crate.
+///
+/// Besides the kind of the error it also carries a short description
+/// of the operation which failed, e.g. "get the device name".
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    operation: &'static str,
+    source: io::Error
+}
+
+fn kind_of( error: &io::Error ) -> ErrorKind {
+    if let Some( errno ) = error.raw_os_error() {
+        return match errno {
+            libc::EACCES | libc::EPERM => ErrorKind::PermissionDenied,
+            libc::ENODEV | libc::ENXIO | libc::ENOENT => ErrorKind::DeviceGone,
+            libc::ENOTTY | libc::EOPNOTSUPP | libc::ENOSYS => ErrorKind::NotSupported,
+            libc::EBUSY => ErrorKind::Busy,
+            libc::EINVAL => ErrorKind::InvalidArgument,
+            libc::ETIMEDOUT => ErrorKind::TimedOut,
+            _ => ErrorKind::Other
+        };
+    }
+
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        io::ErrorKind::NotFound => ErrorKind::DeviceGone,
+        io::ErrorKind::Unsupported => ErrorKind::NotSupported,
+        io::ErrorKind::InvalidInput => ErrorKind::InvalidArgument,
+        io::ErrorKind::TimedOut => ErrorKind::TimedOut,
+        _ => ErrorKind::Other
+    }
+}
+
+fn nix_to_io( error: nix::Error ) -> io::Error {
+    match error {
+        nix::Error::Sys( errno ) => io::Error::from_raw_os_error( errno as i32 ),
+        nix::Error::UnsupportedOperation => io::Error::new( io::ErrorKind::Unsupported, error.to_string() ),
+        nix::Error::InvalidPath | nix::Error::InvalidUtf8 => io::Error::new( io::ErrorKind::InvalidData, error.to_string() )
+    }
+}
+
+impl Error {
+    pub(crate) fn new( kind: ErrorKind, operation: &'static str, source: io::Error ) -> Self {
+        Error {
+            kind,
+            operation,
+            source
+        }
+    }
+
+    pub(crate) fn from_io( operation: &'static str, source: io::Error ) -> Self {
+        Error::new( kind_of( &source ), operation, source )
+    }
+
+    pub(crate) fn from_nix( operation: &'static str, error: nix::Error ) -> Self {
+        Error::from_io( operation, nix_to_io( error ) )
+    }
+
+    pub(crate) fn last_os_error( operation: &'static str ) -> Self {
+        Error::from_io( operation, io::Error::last_os_error() )
+    }
+
+    pub(crate) fn invalid_argument( operation: &'static str, message: String ) -> Self {
+        Error::new( ErrorKind::InvalidArgument, operation, io::Error::new( io::ErrorKind::InvalidInput, message ) )
+    }
+
+    pub(crate) fn device_create( operation: &'static str, error: DeviceCreateError ) -> Self {
+        Error::new( ErrorKind::InvalidArgument, operation, io::Error::new( io::ErrorKind::InvalidInput, error ) )
+    }
+
+    pub(crate) fn other( operation: &'static str, message: String ) -> Self {
+        Error::new( ErrorKind::Other, operation, io::Error::new( io::ErrorKind::Other, message ) )
+    }
+
+    pub(crate) fn into_revoked( self ) -> Self {
+        Error {
+            kind: ErrorKind::Revoked,
+            ..self
+        }
+    }
+
+    pub fn kind( &self ) -> ErrorKind {
+        self.kind
+    }
+
+    /// A short description of the operation which failed.
+    pub fn operation( &self ) -> &'static str {
+        self.operation
+    }
+
+    /// The OS error code, if the error was reported by the kernel.
+    pub fn raw_os_error( &self ) -> Option< i32 > {
+        self.source.raw_os_error()
+    }
+
+    /// The reason why a `VirtualDeviceBuilder` was rejected, if that's what failed.
+    pub fn device_create_error( &self ) -> Option< &DeviceCreateError > {
+        self.source.get_ref()?.downcast_ref()
+    }
+
+    pub(crate) fn errno( &self ) -> nix::errno::Errno {
+        self.raw_os_error().map( nix::errno::Errno::from_i32 ).unwrap_or( nix::errno::Errno::EIO )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Revoked => write!( fmt, "failed to {}: access to the device was revoked", self.operation ),
+            _ => write!( fmt, "failed to {}: {}", self.operation, self.source )
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source( &self ) -> Option< &(dyn std::error::Error + 'static) > {
+        Some( &self.source )
+    }
+}
+
+impl From< Error > for io::Error {
+    fn from( error: Error ) -> Self {
+        let kind = match error.kind {
+            ErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+            ErrorKind::DeviceGone => io::ErrorKind::NotFound,
+            ErrorKind::NotSupported => io::ErrorKind::Unsupported,
+            ErrorKind::InvalidArgument => io::ErrorKind::InvalidInput,
+            ErrorKind::TimedOut => io::ErrorKind::TimedOut,
+            // `io::ErrorKind::ResourceBusy` needs a newer Rust than we support.
+            ErrorKind::Busy | ErrorKind::Revoked | ErrorKind::Other => io::ErrorKind::Other
+        };
+
+        io::Error::new( kind, error )
+    }
+}
+
+/// Attaches the failing operation to a lower level error.
+pub(crate) trait Context< T > {
+    fn context( self, operation: &'static str ) -> Result< T, Error >;
+}
+
+impl< T > Context< T > for Result< T, nix::Error > {
+    fn context( self, operation: &'static str ) -> Result< T, Error > {
+        self.map_err( |error| Error::from_nix( operation, error ) )
+    }
+}
+
+impl< T > Context< T > for Result< T, io::Error > {
+    fn context( self, operation: &'static str ) -> Result< T, Error > {
+        self.map_err( |error| Error::from_io( operation, error ) )
+    }
+}
+
+#[test]
+fn test_error_kinds() {
+    let error = Error::from_nix( "grab the device", nix::Error::Sys( nix::errno::Errno::EBUSY ) );
+    assert_eq!( error.kind(), ErrorKind::Busy );
+    assert_eq!( error.operation(), "grab the device" );
+    assert_eq!( error.raw_os_error(), Some( libc::EBUSY ) );
+
+    assert_eq!( Error::from_nix( "", nix::Error::Sys( nix::errno::Errno::EACCES ) ).kind(), ErrorKind::PermissionDenied );
+    assert_eq!( Error::from_nix( "", nix::Error::Sys( nix::errno::Errno::ENODEV ) ).kind(), ErrorKind::DeviceGone );
+    assert_eq!( Error::from_nix( "", nix::Error::Sys( nix::errno::Errno::ENOTTY ) ).kind(), ErrorKind::NotSupported );
+    assert_eq!( Error::from_nix( "", nix::Error::Sys( nix::errno::Errno::EINVAL ) ).kind(), ErrorKind::InvalidArgument );
+    assert_eq!( Error::from_nix( "", nix::Error::UnsupportedOperation ).kind(), ErrorKind::NotSupported );
+    assert_eq!( Error::from_nix( "", nix::Error::Sys( nix::errno::Errno::ENODEV ) ).into_revoked().kind(), ErrorKind::Revoked );
+
+    let error: std::io::Error = Error::from_nix( "open the device", nix::Error::Sys( nix::errno::Errno::EACCES ) ).into();
+    assert_eq!( error.kind(), io::ErrorKind::PermissionDenied );
+}
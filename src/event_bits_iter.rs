@@ -63,39 +63,39 @@ impl< 'a, T > FusedIterator for EventBitsIter< 'a, T > where T: From< u16 > {}
 
 #[test]
 fn test_event_bits_iter_empty() {
-    let mut iter = EventBitsIter::< u16 >::new( &[] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[] ) );
     assert_eq!( iter.next(), None );
 }
 
 #[test]
 fn test_event_bits_iter_single_element_empty() {
-    let mut iter = EventBitsIter::< u16 >::new( &[0] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[0] ) );
     assert_eq!( iter.next(), None );
 }
 
 #[test]
 fn test_event_bits_iter_multiple_elements_empty() {
-    let mut iter = EventBitsIter::< u16 >::new( &[0, 0, 0, 0] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[0, 0, 0, 0] ) );
     assert_eq!( iter.next(), None );
 }
 
 #[test]
 fn test_event_bits_iter_single_element_first_bit() {
-    let mut iter = EventBitsIter::< u16 >::new( &[0b0000_0001] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[0b0000_0001] ) );
     assert_eq!( iter.next(), Some( 0 ) );
     assert_eq!( iter.next(), None );
 }
 
 #[test]
 fn test_event_bits_iter_single_element_last_bit() {
-    let mut iter = EventBitsIter::< u16 >::new( &[0b1000_0000] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[0b1000_0000] ) );
     assert_eq!( iter.next(), Some( 7 ) );
     assert_eq!( iter.next(), None );
 }
 
 #[test]
 fn test_event_bits_iter_single_element_multiple_bits() {
-    let mut iter = EventBitsIter::< u16 >::new( &[0b1000_0001] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[0b1000_0001] ) );
     assert_eq!( iter.next(), Some( 0 ) );
     assert_eq!( iter.next(), Some( 7 ) );
     assert_eq!( iter.next(), None );
@@ -103,7 +103,7 @@ fn test_event_bits_iter_single_element_multiple_bits() {
 
 #[test]
 fn test_event_bits_iter_single_element_multiple_elements() {
-    let mut iter = EventBitsIter::< u16 >::new( &[0, 0b1000_0001, 0] );
+    let mut iter = EventBitsIter::< u16 >::new( Cow::Borrowed( &[0, 0b1000_0001, 0] ) );
     assert_eq!( iter.next(), Some( 8 ) );
     assert_eq!( iter.next(), Some( 15 ) );
     assert_eq!( iter.next(), None );
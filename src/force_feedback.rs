@@ -0,0 +1,722 @@
+use {
+    std::{
+        time::{
+            Duration
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            Device,
+            InputEventBody
+        },
+        input_sys::{
+            self,
+            ForceFeedback,
+            ForceFeedbackWaveform,
+            Key,
+            RawForceFeedbackBody,
+            RawForceFeedbackConditionEffect,
+            RawForceFeedbackConstantEffect,
+            RawForceFeedbackEffect,
+            RawForceFeedbackEnvelope,
+            RawForceFeedbackPeriodicEffect,
+            RawForceFeedbackRampEffect,
+            RawForceFeedbackReplay,
+            RawForceFeedbackRumbleEffect,
+            RawForceFeedbackTrigger
+        }
+    }
+};
+
+/// Shapes the start and the end of an effect.
+///
+/// The levels are absolute; the effect starts at `attack_level`, reaches
+/// its nominal level after `attack_length`, and ends at `fade_level`
+/// after fading for `fade_length`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForceFeedbackEnvelope {
+    pub attack_length: Duration,
+    pub attack_level: u16,
+    pub fade_length: Duration,
+    pub fade_level: u16
+}
+
+impl From< RawForceFeedbackEnvelope > for ForceFeedbackEnvelope {
+    fn from( envelope: RawForceFeedbackEnvelope ) -> Self {
+        ForceFeedbackEnvelope {
+            attack_length: Duration::from_millis( envelope.attack_length as u64 ),
+            attack_level: envelope.attack_level,
+            fade_length: Duration::from_millis( envelope.fade_length as u64 ),
+            fade_level: envelope.fade_level
+        }
+    }
+}
+
+impl From< ForceFeedbackEnvelope > for RawForceFeedbackEnvelope {
+    fn from( envelope: ForceFeedbackEnvelope ) -> Self {
+        RawForceFeedbackEnvelope {
+            attack_length: convert_and_clip( envelope.attack_length ),
+            attack_level: envelope.attack_level,
+            fade_length: convert_and_clip( envelope.fade_length ),
+            fade_level: envelope.fade_level
+        }
+    }
+}
+
+/// Parameters of a condition effect along a single axis.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForceFeedbackCondition {
+    /// The maximum level when the joystick is moved all the way to the right.
+    pub right_saturation: u16,
+    /// The same as `right_saturation`, but for the left side.
+    pub left_saturation: u16,
+    /// Controls how fast the force grows when the joystick moves to the right.
+    pub right_coefficient: i16,
+    /// The same as `right_coefficient`, but for the left side.
+    pub left_coefficient: i16,
+    /// The size of the dead zone, where no force is produced.
+    pub deadband: u16,
+    /// The position of the dead zone.
+    pub center: i16
+}
+
+impl From< RawForceFeedbackConditionEffect > for ForceFeedbackCondition {
+    fn from( condition: RawForceFeedbackConditionEffect ) -> Self {
+        ForceFeedbackCondition {
+            right_saturation: condition.right_saturation,
+            left_saturation: condition.left_saturation,
+            right_coefficient: condition.right_coefficient,
+            left_coefficient: condition.left_coefficient,
+            deadband: condition.deadband,
+            center: condition.center
+        }
+    }
+}
+
+impl From< ForceFeedbackCondition > for RawForceFeedbackConditionEffect {
+    fn from( condition: ForceFeedbackCondition ) -> Self {
+        RawForceFeedbackConditionEffect {
+            right_saturation: condition.right_saturation,
+            left_saturation: condition.left_saturation,
+            right_coefficient: condition.right_coefficient,
+            left_coefficient: condition.left_coefficient,
+            deadband: condition.deadband,
+            center: condition.center
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ForceFeedbackEffectKind {
+    Rumble {
+        strong_magnitude: u16,
+        weak_magnitude: u16
+    },
+    Periodic {
+        waveform: ForceFeedbackWaveform,
+        period: Duration,
+        /// The peak value.
+        magnitude: i16,
+        /// The mean value of the wave.
+        offset: i16,
+        /// The horizontal shift.
+        phase: u16,
+        envelope: ForceFeedbackEnvelope,
+        /// The samples of a single period of the wave; only used with `ForceFeedbackWaveform::Custom`.
+        custom_data: Vec< i16 >
+    },
+    Constant {
+        level: i16,
+        envelope: ForceFeedbackEnvelope
+    },
+    Ramp {
+        start_level: i16,
+        end_level: i16,
+        envelope: ForceFeedbackEnvelope
+    },
+    /// Pulls the joystick towards the center.
+    ///
+    /// The first condition applies to the X axis, the second one to the Y axis.
+    Spring {
+        conditions: [ForceFeedbackCondition; 2]
+    },
+    /// Resists the joystick's movement.
+    Friction {
+        conditions: [ForceFeedbackCondition; 2]
+    },
+    /// Resists the joystick's movement proportionally to its velocity.
+    Damper {
+        conditions: [ForceFeedbackCondition; 2]
+    },
+    /// Resists the joystick's movement proportionally to its acceleration.
+    Inertia {
+        conditions: [ForceFeedbackCondition; 2]
+    }
+}
+
+impl ForceFeedbackEffectKind {
+    unsafe fn from_raw( kind: u16, body: &RawForceFeedbackBody ) -> Option< Self > {
+        let conditions = || [body.condition[ 0 ].into(), body.condition[ 1 ].into()];
+        let kind = match kind {
+            input_sys::FF_RUMBLE => {
+                let raw_effect = &body.rumble;
+                ForceFeedbackEffectKind::Rumble {
+                    strong_magnitude: raw_effect.strong_magnitude,
+                    weak_magnitude: raw_effect.weak_magnitude
+                }
+            },
+            input_sys::FF_PERIODIC => {
+                let raw_effect = &body.periodic;
+                ForceFeedbackEffectKind::Periodic {
+                    waveform: raw_effect.waveform.into(),
+                    period: Duration::from_millis( raw_effect.period as u64 ),
+                    magnitude: raw_effect.magnitude,
+                    offset: raw_effect.offset,
+                    phase: raw_effect.phase,
+                    envelope: raw_effect.envelope.into(),
+                    // The samples always live in the memory of the process
+                    // which uploaded the effect, so we can't access them.
+                    custom_data: Vec::new()
+                }
+            },
+            input_sys::FF_CONSTANT => {
+                let raw_effect = &body.constant;
+                ForceFeedbackEffectKind::Constant {
+                    level: raw_effect.level,
+                    envelope: raw_effect.envelope.into()
+                }
+            },
+            input_sys::FF_RAMP => {
+                let raw_effect = &body.ramp;
+                ForceFeedbackEffectKind::Ramp {
+                    start_level: raw_effect.start_level,
+                    end_level: raw_effect.end_level,
+                    envelope: raw_effect.envelope.into()
+                }
+            },
+            input_sys::FF_SPRING => ForceFeedbackEffectKind::Spring { conditions: conditions() },
+            input_sys::FF_FRICTION => ForceFeedbackEffectKind::Friction { conditions: conditions() },
+            input_sys::FF_DAMPER => ForceFeedbackEffectKind::Damper { conditions: conditions() },
+            input_sys::FF_INERTIA => ForceFeedbackEffectKind::Inertia { conditions: conditions() },
+            _ => return None
+        };
+
+        Some( kind )
+    }
+
+    fn raw_kind( &self ) -> u16 {
+        match *self {
+            ForceFeedbackEffectKind::Rumble { .. } => input_sys::FF_RUMBLE,
+            ForceFeedbackEffectKind::Periodic { .. } => input_sys::FF_PERIODIC,
+            ForceFeedbackEffectKind::Constant { .. } => input_sys::FF_CONSTANT,
+            ForceFeedbackEffectKind::Ramp { .. } => input_sys::FF_RAMP,
+            ForceFeedbackEffectKind::Spring { .. } => input_sys::FF_SPRING,
+            ForceFeedbackEffectKind::Friction { .. } => input_sys::FF_FRICTION,
+            ForceFeedbackEffectKind::Damper { .. } => input_sys::FF_DAMPER,
+            ForceFeedbackEffectKind::Inertia { .. } => input_sys::FF_INERTIA
+        }
+    }
+
+    // The returned body borrows `self`'s custom waveform data, if any.
+    fn raw_body( &self ) -> RawForceFeedbackBody {
+        match *self {
+            ForceFeedbackEffectKind::Rumble { weak_magnitude, strong_magnitude } => {
+                RawForceFeedbackBody {
+                    rumble: RawForceFeedbackRumbleEffect {
+                        weak_magnitude, strong_magnitude
+                    }
+                }
+            },
+            ForceFeedbackEffectKind::Periodic { waveform, period, magnitude, offset, phase, envelope, ref custom_data } => {
+                let (custom_length, custom_data) = if waveform == ForceFeedbackWaveform::Custom && !custom_data.is_empty() {
+                    (custom_data.len() as u32, custom_data.as_ptr() as *mut i16)
+                } else {
+                    (0, std::ptr::null_mut())
+                };
+
+                RawForceFeedbackBody {
+                    periodic: RawForceFeedbackPeriodicEffect {
+                        waveform: waveform.raw(),
+                        period: convert_and_clip( period ),
+                        magnitude,
+                        offset,
+                        phase,
+                        envelope: envelope.into(),
+                        custom_length,
+                        custom_data
+                    }
+                }
+            },
+            ForceFeedbackEffectKind::Constant { level, envelope } => {
+                RawForceFeedbackBody {
+                    constant: RawForceFeedbackConstantEffect {
+                        level,
+                        envelope: envelope.into()
+                    }
+                }
+            },
+            ForceFeedbackEffectKind::Ramp { start_level, end_level, envelope } => {
+                RawForceFeedbackBody {
+                    ramp: RawForceFeedbackRampEffect {
+                        start_level,
+                        end_level,
+                        envelope: envelope.into()
+                    }
+                }
+            },
+            ForceFeedbackEffectKind::Spring { conditions } |
+            ForceFeedbackEffectKind::Friction { conditions } |
+            ForceFeedbackEffectKind::Damper { conditions } |
+            ForceFeedbackEffectKind::Inertia { conditions } => {
+                RawForceFeedbackBody {
+                    condition: [conditions[ 0 ].into(), conditions[ 1 ].into()]
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ForceFeedbackDuration {
+    Finite( Duration ),
+    Infinite
+}
+
+/// Makes an effect start automatically when a given button is pressed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForceFeedbackTrigger {
+    /// The button which triggers the effect; `None` if the effect is not triggered by any button.
+    pub button: Option< Key >,
+    /// The minimum time between two consecutive triggers of the effect.
+    pub interval: Duration
+}
+
+impl From< RawForceFeedbackTrigger > for ForceFeedbackTrigger {
+    fn from( trigger: RawForceFeedbackTrigger ) -> Self {
+        ForceFeedbackTrigger {
+            button: match trigger.button {
+                0 => None,
+                button => Some( button.into() )
+            },
+            interval: Duration::from_millis( trigger.interval as u64 )
+        }
+    }
+}
+
+impl From< ForceFeedbackTrigger > for RawForceFeedbackTrigger {
+    fn from( trigger: ForceFeedbackTrigger ) -> Self {
+        RawForceFeedbackTrigger {
+            button: trigger.button.map( |button| button.raw() ).unwrap_or( 0 ),
+            interval: convert_and_clip( trigger.interval )
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ForceFeedbackEffect {
+    pub id: i16,
+    pub direction: u16,
+    pub kind: ForceFeedbackEffectKind,
+    pub duration: ForceFeedbackDuration,
+    pub delay: Duration,
+    pub trigger: ForceFeedbackTrigger
+}
+
+impl ForceFeedbackEffect {
+    /// Converts a raw effect; returns `None` if the effect is of an unknown kind.
+    pub(crate) unsafe fn from_raw( raw_effect: &RawForceFeedbackEffect ) -> Option< Self > {
+        let effect = ForceFeedbackEffect {
+            id: raw_effect.id,
+            direction: raw_effect.direction,
+            kind: ForceFeedbackEffectKind::from_raw( raw_effect.kind, &raw_effect.body )?,
+            trigger: raw_effect.trigger.into(),
+            duration: match raw_effect.replay.length {
+                0 => ForceFeedbackDuration::Infinite,
+                length => ForceFeedbackDuration::Finite( Duration::from_millis( length as u64 ) )
+            },
+            delay: Duration::from_millis( raw_effect.replay.delay as u64 )
+        };
+
+        Some( effect )
+    }
+
+    /// Converts the effect into its raw form and passes it to `callback`.
+    ///
+    /// The raw effect can point to the samples of a custom waveform,
+    /// so it must not outlive the callback.
+    pub(crate) fn with_raw< R >( &self, callback: impl FnOnce( &RawForceFeedbackEffect ) -> R ) -> R {
+        let raw_effect = RawForceFeedbackEffect {
+            id: self.id,
+            direction: self.direction,
+            trigger: self.trigger.into(),
+            replay: RawForceFeedbackReplay {
+                length: match self.duration {
+                    ForceFeedbackDuration::Finite( duration ) => convert_and_clip( duration ),
+                    ForceFeedbackDuration::Infinite => 0
+                },
+                delay: convert_and_clip( self.delay )
+            },
+            body: self.kind.raw_body(),
+            kind: self.kind.raw_kind()
+        };
+
+        callback( &raw_effect )
+    }
+}
+
+fn convert_and_clip( duration: Duration ) -> u16 {
+    let duration = duration.as_millis();
+    if duration > 0x7fff {
+        0x7fff
+    } else {
+        duration as u16
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ForceFeedbackEffectId( pub(crate) i16 );
+
+// Mirrors the kernel's `check_effects_compatible`.
+fn check_compatible( old: &ForceFeedbackEffectKind, new: &ForceFeedbackEffectKind ) -> Result< (), Error > {
+    if old.raw_kind() != new.raw_kind() {
+        return Err( Error::invalid_argument( "update a force feedback effect", "the type of an uploaded effect can't be changed".to_owned() ) );
+    }
+
+    if let (ForceFeedbackEffectKind::Periodic { waveform: old, .. }, ForceFeedbackEffectKind::Periodic { waveform: new, .. }) = (old, new) {
+        if old != new {
+            return Err( Error::invalid_argument( "update a force feedback effect", "the waveform of an uploaded effect can't be changed".to_owned() ) );
+        }
+    }
+
+    Ok(())
+}
+
+/// A handle to an effect uploaded into a `Device`.
+///
+/// The effect is erased from the device when this is dropped.
+pub struct Effect< 'a > {
+    device: &'a Device,
+    id: ForceFeedbackEffectId,
+    effect: ForceFeedbackEffect,
+    erase_on_drop: bool
+}
+
+impl< 'a > Effect< 'a > {
+    pub(crate) fn new( device: &'a Device, id: ForceFeedbackEffectId, mut effect: ForceFeedbackEffect ) -> Self {
+        effect.id = id.0;
+        Effect {
+            device,
+            id,
+            effect,
+            erase_on_drop: true
+        }
+    }
+
+    pub fn id( &self ) -> ForceFeedbackEffectId {
+        self.id
+    }
+
+    /// The effect as it was last uploaded.
+    pub fn effect( &self ) -> &ForceFeedbackEffect {
+        &self.effect
+    }
+
+    /// Changes the parameters of the effect in-place.
+    ///
+    /// The effect keeps its ID, and if it's currently playing it
+    /// will continue to do so with the new parameters.
+    ///
+    /// The kernel doesn't allow an uploaded effect to change its type, or
+    /// the waveform of a periodic effect; such updates fail with
+    /// `ErrorKind::InvalidArgument`, and the effect has to be uploaded anew.
+    pub fn update( &mut self, kind: ForceFeedbackEffectKind ) -> Result< (), Error > {
+        let mut effect = self.effect.clone();
+        effect.kind = kind;
+        self.replace( effect )
+    }
+
+    /// Replaces the whole effect in-place, keeping its ID.
+    ///
+    /// The same restrictions as for `update` apply.
+    pub fn replace( &mut self, mut effect: ForceFeedbackEffect ) -> Result< (), Error > {
+        check_compatible( &self.effect.kind, &effect.kind )?;
+        effect.id = self.id.0;
+        self.device.upload_force_feedback_effect_with_id( &effect, self.id.0 )?;
+        self.effect = effect;
+        Ok(())
+    }
+
+    /// Starts playing the effect `count` times in a row.
+    pub fn play( &self, count: i32 ) -> Result< (), Error > {
+        self.device.enable_force_feedback_effect( self.id, count )
+    }
+
+    pub fn stop( &self ) -> Result< (), Error > {
+        self.device.disable_force_feedback_effect( self.id )
+    }
+
+    /// Checks whether a given event reports the status of this effect.
+    ///
+    /// Returns `Some( true )` if the effect started playing, `Some( false )`
+    /// if it stopped, and `None` if the event is about something else.
+    pub fn status_from_event( &self, event: &InputEventBody ) -> Option< bool > {
+        match *event {
+            InputEventBody::EffectStatus { effect_id, playing } if effect_id == self.id.0 as u16 => Some( playing ),
+            _ => None
+        }
+    }
+
+    /// Releases the handle without erasing the effect from the device.
+    pub fn into_id( mut self ) -> ForceFeedbackEffectId {
+        self.erase_on_drop = false;
+        self.id
+    }
+}
+
+impl< 'a > Drop for Effect< 'a > {
+    fn drop( &mut self ) {
+        if self.erase_on_drop {
+            let _ = self.device.erase_force_feedback_effect( self.id );
+        }
+    }
+}
+
+/// The force feedback features supported by a device.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForceFeedbackCapabilities {
+    /// The kinds of effects which can be uploaded.
+    pub effects: Vec< ForceFeedback >,
+    /// The waveforms which can be used by periodic effects.
+    pub waveforms: Vec< ForceFeedbackWaveform >,
+    /// Whether the global gain can be changed.
+    pub has_gain: bool,
+    /// Whether the autocenter strength can be changed.
+    pub has_autocenter: bool,
+    /// The maximum number of effects which can be played at the same time.
+    pub max_simultaneous_effects: u32
+}
+
+impl ForceFeedbackCapabilities {
+    pub(crate) fn from_bits( bits: impl IntoIterator< Item = ForceFeedback >, max_simultaneous_effects: u32 ) -> Self {
+        let mut capabilities = ForceFeedbackCapabilities {
+            max_simultaneous_effects,
+            .. ForceFeedbackCapabilities::default()
+        };
+
+        for bit in bits {
+            match bit.raw() {
+                input_sys::FF_RUMBLE..=input_sys::FF_RAMP => capabilities.effects.push( bit ),
+                input_sys::FF_WAVEFORM_MIN..=input_sys::FF_WAVEFORM_MAX => capabilities.waveforms.push( bit.raw().into() ),
+                input_sys::FF_GAIN => capabilities.has_gain = true,
+                input_sys::FF_AUTOCENTER => capabilities.has_autocenter = true,
+                _ => {}
+            }
+        }
+
+        capabilities
+    }
+}
+
+/// Converts a fraction in the `0.0..=1.0` range into the range used by the kernel for the gain and the autocenter.
+pub(crate) fn fraction_to_raw( value: f32 ) -> i32 {
+    (value.clamp( 0.0, 1.0 ) * 65535.0).round() as i32
+}
+
+pub(crate) fn raw_to_fraction( value: i32 ) -> f32 {
+    value.clamp( 0, 0xffff ) as f32 / 65535.0
+}
+
+#[cfg(test)]
+fn assert_round_trip( kind: ForceFeedbackEffectKind ) {
+    let effect = ForceFeedbackEffect {
+        id: 3,
+        direction: 0x4000,
+        kind,
+        duration: ForceFeedbackDuration::Finite( Duration::from_millis( 250 ) ),
+        delay: Duration::from_millis( 10 ),
+        trigger: ForceFeedbackTrigger {
+            button: Some( Key::PadSouth ),
+            interval: Duration::from_millis( 100 )
+        }
+    };
+
+    let converted = effect.with_raw( |raw_effect| unsafe { ForceFeedbackEffect::from_raw( raw_effect ) } );
+    assert_eq!( converted, Some( effect ) );
+}
+
+#[cfg(test)]
+fn test_envelope() -> ForceFeedbackEnvelope {
+    ForceFeedbackEnvelope {
+        attack_length: Duration::from_millis( 20 ),
+        attack_level: 0x1000,
+        fade_length: Duration::from_millis( 50 ),
+        fade_level: 0
+    }
+}
+
+#[test]
+fn test_force_feedback_rumble_round_trip() {
+    assert_round_trip( ForceFeedbackEffectKind::Rumble {
+        strong_magnitude: 0xc000,
+        weak_magnitude: 0x4000
+    });
+}
+
+#[test]
+fn test_force_feedback_periodic_round_trip() {
+    for &waveform in &[
+        ForceFeedbackWaveform::Square,
+        ForceFeedbackWaveform::Triangle,
+        ForceFeedbackWaveform::Sine,
+        ForceFeedbackWaveform::SawUp,
+        ForceFeedbackWaveform::SawDown
+    ] {
+        assert_round_trip( ForceFeedbackEffectKind::Periodic {
+            waveform,
+            period: Duration::from_millis( 100 ),
+            magnitude: -0x2000,
+            offset: 0x100,
+            phase: 0x4000,
+            envelope: test_envelope(),
+            custom_data: Vec::new()
+        });
+    }
+}
+
+#[test]
+fn test_force_feedback_custom_waveform_to_raw() {
+    let samples = vec![ 0, 0x4000, 0x7fff, 0x4000, 0, -0x4000, -0x7fff, -0x4000 ];
+    let effect = ForceFeedbackEffect {
+        id: -1,
+        direction: 0,
+        kind: ForceFeedbackEffectKind::Periodic {
+            waveform: ForceFeedbackWaveform::Custom,
+            period: Duration::from_millis( 80 ),
+            magnitude: 0x7fff,
+            offset: 0,
+            phase: 0,
+            envelope: ForceFeedbackEnvelope::default(),
+            custom_data: samples.clone()
+        },
+        duration: ForceFeedbackDuration::Infinite,
+        delay: Duration::from_millis( 0 ),
+        trigger: ForceFeedbackTrigger::default()
+    };
+
+    let raw_samples = effect.with_raw( |raw_effect| unsafe {
+        assert_eq!( raw_effect.kind, input_sys::FF_PERIODIC );
+        let periodic = &raw_effect.body.periodic;
+        std::slice::from_raw_parts( periodic.custom_data, periodic.custom_length as usize ).to_vec()
+    });
+
+    assert_eq!( raw_samples, samples );
+}
+
+#[test]
+fn test_force_feedback_constant_and_ramp_round_trip() {
+    assert_round_trip( ForceFeedbackEffectKind::Constant {
+        level: -0x7000,
+        envelope: test_envelope()
+    });
+
+    assert_round_trip( ForceFeedbackEffectKind::Ramp {
+        start_level: -0x7000,
+        end_level: 0x7000,
+        envelope: test_envelope()
+    });
+}
+
+#[test]
+fn test_force_feedback_condition_round_trip() {
+    let conditions = [
+        ForceFeedbackCondition {
+            right_saturation: 0xffff,
+            left_saturation: 0x8000,
+            right_coefficient: 0x2000,
+            left_coefficient: -0x2000,
+            deadband: 0x100,
+            center: -0x10
+        },
+        ForceFeedbackCondition::default()
+    ];
+
+    assert_round_trip( ForceFeedbackEffectKind::Spring { conditions } );
+    assert_round_trip( ForceFeedbackEffectKind::Friction { conditions } );
+    assert_round_trip( ForceFeedbackEffectKind::Damper { conditions } );
+    assert_round_trip( ForceFeedbackEffectKind::Inertia { conditions } );
+}
+
+#[test]
+fn test_force_feedback_capabilities_from_bits() {
+    let bits = vec![
+        ForceFeedback::Rumble,
+        ForceFeedback::Periodic,
+        ForceFeedback::Sine,
+        ForceFeedback::Square,
+        ForceFeedback::Gain
+    ];
+
+    let capabilities = ForceFeedbackCapabilities::from_bits( bits, 16 );
+    assert_eq!( capabilities, ForceFeedbackCapabilities {
+        effects: vec![ ForceFeedback::Rumble, ForceFeedback::Periodic ],
+        waveforms: vec![ ForceFeedbackWaveform::Sine, ForceFeedbackWaveform::Square ],
+        has_gain: true,
+        has_autocenter: false,
+        max_simultaneous_effects: 16
+    });
+}
+
+#[test]
+fn test_force_feedback_fraction_conversion() {
+    assert_eq!( fraction_to_raw( 0.0 ), 0 );
+    assert_eq!( fraction_to_raw( 1.0 ), 0xffff );
+    assert_eq!( fraction_to_raw( 2.0 ), 0xffff );
+    assert_eq!( fraction_to_raw( -1.0 ), 0 );
+    assert_eq!( fraction_to_raw( 0.5 ), 0x8000 );
+    assert_eq!( raw_to_fraction( 0xffff ), 1.0 );
+    assert_eq!( raw_to_fraction( 0 ), 0.0 );
+}
+
+#[test]
+fn test_force_feedback_unknown_kind() {
+    let effect = ForceFeedbackEffect {
+        id: 0,
+        direction: 0,
+        kind: ForceFeedbackEffectKind::Rumble { strong_magnitude: 0, weak_magnitude: 0 },
+        duration: ForceFeedbackDuration::Infinite,
+        delay: Duration::from_millis( 0 ),
+        trigger: ForceFeedbackTrigger::default()
+    };
+
+    let mut raw_effect = effect.with_raw( |raw_effect| *raw_effect );
+    raw_effect.kind = 0x5f;
+    assert_eq!( unsafe { ForceFeedbackEffect::from_raw( &raw_effect ) }, None );
+}
+
+#[test]
+fn test_force_feedback_update_compatibility() {
+    let periodic = |waveform, magnitude| ForceFeedbackEffectKind::Periodic {
+        waveform,
+        period: Duration::from_millis( 100 ),
+        magnitude,
+        offset: 0,
+        phase: 0,
+        envelope: ForceFeedbackEnvelope::default(),
+        custom_data: Vec::new()
+    };
+
+    let rumble = ForceFeedbackEffectKind::Rumble { strong_magnitude: 0x8000, weak_magnitude: 0 };
+    let sine = periodic( ForceFeedbackWaveform::Sine, 0x4000 );
+
+    assert!( check_compatible( &rumble, &ForceFeedbackEffectKind::Rumble { strong_magnitude: 0, weak_magnitude: 0x8000 } ).is_ok() );
+    assert!( check_compatible( &sine, &periodic( ForceFeedbackWaveform::Sine, 0x7fff ) ).is_ok() );
+
+    let error = check_compatible( &rumble, &sine ).unwrap_err();
+    assert_eq!( error.kind(), crate::error::ErrorKind::InvalidArgument );
+
+    let error = check_compatible( &sine, &periodic( ForceFeedbackWaveform::Square, 0x4000 ) ).unwrap_err();
+    assert_eq!( error.kind(), crate::error::ErrorKind::InvalidArgument );
+}
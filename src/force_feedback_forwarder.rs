@@ -0,0 +1,233 @@
+use {
+    std::{
+        collections::{
+            HashMap
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        force_feedback::{
+            ForceFeedbackEffect,
+            ForceFeedbackEffectId
+        },
+        input::{
+            Device
+        },
+        uinput::{
+            ForceFeedbackRequest
+        }
+    },
+    nix::{
+        errno::{
+            Errno
+        }
+    }
+};
+
+// The part of a `Device` which the effects are forwarded into; separated out so that
+// the mapping of the effect IDs can be tested without a physical device.
+trait ForceFeedbackTarget {
+    fn upload( &self, effect: &ForceFeedbackEffect, id: i16 ) -> Result< ForceFeedbackEffectId, Error >;
+    fn erase( &self, id: ForceFeedbackEffectId ) -> Result< (), Error >;
+}
+
+impl ForceFeedbackTarget for Device {
+    fn upload( &self, effect: &ForceFeedbackEffect, id: i16 ) -> Result< ForceFeedbackEffectId, Error > {
+        self.upload_force_feedback_effect_with_id( effect, id )
+    }
+
+    fn erase( &self, id: ForceFeedbackEffectId ) -> Result< (), Error > {
+        self.erase_force_feedback_effect( id )
+    }
+}
+
+/// Uploads an effect into the target, reusing the physical effect if the virtual one was already uploaded.
+///
+/// On failure returns the errno with which the upload into the virtual device should be rejected.
+fn forward_upload(
+    target: &impl ForceFeedbackTarget,
+    effects: &mut HashMap< u16, ForceFeedbackEffectId >,
+    virtual_effect_id: u16,
+    effect: Option< ForceFeedbackEffect >
+) -> Result< (), Errno > {
+    let effect = effect.ok_or( Errno::EINVAL )?;
+    let physical_effect_id = effects.get( &virtual_effect_id ).map( |id| id.0 ).unwrap_or( -1 );
+    let physical_effect_id = target.upload( &effect, physical_effect_id ).map_err( |error| error.errno() )?;
+    effects.insert( virtual_effect_id, physical_effect_id );
+    Ok(())
+}
+
+fn forward_erase(
+    target: &impl ForceFeedbackTarget,
+    effects: &mut HashMap< u16, ForceFeedbackEffectId >,
+    virtual_effect_id: u16
+) -> Result< (), Errno > {
+    match effects.remove( &virtual_effect_id ) {
+        Some( physical_effect_id ) => target.erase( physical_effect_id ).map_err( |error| error.errno() ),
+        None => Ok(())
+    }
+}
+
+/// Forwards the force feedback requests received by a `VirtualDevice` into a physical `Device`.
+///
+/// This is useful when a virtual device sits in front of a real one;
+/// the effects uploaded into the virtual device are uploaded into the
+/// real device, and any errors reported by the real device are passed
+/// back to the process which uploaded the effect.
+///
+/// Every effect uploaded through the forwarder is erased from the
+/// physical device when the forwarder is dropped.
+pub struct ForceFeedbackForwarder< 'a > {
+    device: &'a Device,
+    effects: HashMap< u16, ForceFeedbackEffectId >
+}
+
+impl< 'a > ForceFeedbackForwarder< 'a > {
+    pub fn new( device: &'a Device ) -> Self {
+        ForceFeedbackForwarder {
+            device,
+            effects: HashMap::new()
+        }
+    }
+
+    /// Returns the ID of the physical effect corresponding to a given virtual effect.
+    pub fn physical_effect_id( &self, virtual_effect_id: u16 ) -> Option< ForceFeedbackEffectId > {
+        self.effects.get( &virtual_effect_id ).cloned()
+    }
+
+    /// Handles a request received from `VirtualDevice::poll_force_feedback`.
+    ///
+    /// Uploads and erasures are always completed, successfully or not; an error
+    /// is returned only if the virtual device itself failed, or if an event
+    /// couldn't be forwarded into the physical device.
+    pub fn forward( &mut self, request: ForceFeedbackRequest ) -> Result< (), Error > {
+        match request {
+            ForceFeedbackRequest::Upload( upload ) => {
+                match forward_upload( self.device, &mut self.effects, upload.effect_id(), upload.effect() ) {
+                    Ok(()) => upload.complete(),
+                    Err( errno ) => upload.fail( errno )
+                }
+            },
+            ForceFeedbackRequest::Erase( erase ) => {
+                match forward_erase( self.device, &mut self.effects, erase.effect_id() ) {
+                    Ok(()) => erase.complete(),
+                    Err( errno ) => erase.fail( errno )
+                }
+            },
+            ForceFeedbackRequest::Enable { effect_id, cycle_count } => {
+                match self.effects.get( &effect_id ) {
+                    Some( &physical_effect_id ) => self.device.enable_force_feedback_effect( physical_effect_id, cycle_count ),
+                    None => Ok(())
+                }
+            },
+            ForceFeedbackRequest::Disable { effect_id } => {
+                match self.effects.get( &effect_id ) {
+                    Some( &physical_effect_id ) => self.device.disable_force_feedback_effect( physical_effect_id ),
+                    None => Ok(())
+                }
+            },
+            ForceFeedbackRequest::Gain { gain } => self.device.set_force_feedback_gain( gain ),
+            ForceFeedbackRequest::Autocenter { strength } => self.device.set_autocenter( strength ),
+            ForceFeedbackRequest::Other { .. } => Ok(())
+        }
+    }
+}
+
+impl< 'a > Drop for ForceFeedbackForwarder< 'a > {
+    fn drop( &mut self ) {
+        for (_, physical_effect_id) in self.effects.drain() {
+            let _ = self.device.erase_force_feedback_effect( physical_effect_id );
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct TestTarget {
+    uploads: std::cell::RefCell< Vec< i16 > >,
+    erasures: std::cell::RefCell< Vec< i16 > >,
+    next_id: std::cell::Cell< i16 >,
+    errno: Option< Errno >
+}
+
+#[cfg(test)]
+impl ForceFeedbackTarget for TestTarget {
+    fn upload( &self, _: &ForceFeedbackEffect, id: i16 ) -> Result< ForceFeedbackEffectId, Error > {
+        self.uploads.borrow_mut().push( id );
+        if let Some( errno ) = self.errno {
+            return Err( Error::from_nix( "upload a force feedback effect", nix::Error::Sys( errno ) ) );
+        }
+
+        if id >= 0 {
+            return Ok( ForceFeedbackEffectId( id ) );
+        }
+
+        let id = self.next_id.get();
+        self.next_id.set( id + 1 );
+        Ok( ForceFeedbackEffectId( id ) )
+    }
+
+    fn erase( &self, id: ForceFeedbackEffectId ) -> Result< (), Error > {
+        self.erasures.borrow_mut().push( id.0 );
+        match self.errno {
+            Some( errno ) => Err( Error::from_nix( "erase a force feedback effect", nix::Error::Sys( errno ) ) ),
+            None => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_effect() -> Option< ForceFeedbackEffect > {
+    Some( ForceFeedbackEffect {
+        id: -1,
+        direction: 0,
+        kind: crate::force_feedback::ForceFeedbackEffectKind::Rumble { strong_magnitude: 0x8000, weak_magnitude: 0 },
+        duration: crate::force_feedback::ForceFeedbackDuration::Infinite,
+        delay: std::time::Duration::from_millis( 0 ),
+        trigger: Default::default()
+    })
+}
+
+#[test]
+fn test_forwarder_effect_id_mapping() {
+    let target = TestTarget { next_id: std::cell::Cell::new( 10 ), ..TestTarget::default() };
+    let mut effects = HashMap::new();
+
+    assert_eq!( forward_upload( &target, &mut effects, 0, test_effect() ), Ok(()) );
+    assert_eq!( forward_upload( &target, &mut effects, 3, test_effect() ), Ok(()) );
+    assert_eq!( effects.get( &0 ), Some( &ForceFeedbackEffectId( 10 ) ) );
+    assert_eq!( effects.get( &3 ), Some( &ForceFeedbackEffectId( 11 ) ) );
+
+    // Updating a virtual effect updates the physical effect it was mapped to.
+    assert_eq!( forward_upload( &target, &mut effects, 3, test_effect() ), Ok(()) );
+    assert_eq!( *target.uploads.borrow(), vec![ -1, -1, 11 ] );
+    assert_eq!( effects.len(), 2 );
+
+    assert_eq!( forward_erase( &target, &mut effects, 0 ), Ok(()) );
+    assert_eq!( *target.erasures.borrow(), vec![ 10 ] );
+    assert_eq!( effects.get( &0 ), None );
+
+    // Erasing an effect which was never forwarded doesn't touch the physical device.
+    assert_eq!( forward_erase( &target, &mut effects, 5 ), Ok(()) );
+    assert_eq!( *target.erasures.borrow(), vec![ 10 ] );
+}
+
+#[test]
+fn test_forwarder_errno_round_trip() {
+    let target = TestTarget { errno: Some( Errno::ENOSPC ), ..TestTarget::default() };
+    let mut effects = HashMap::new();
+
+    assert_eq!( forward_upload( &target, &mut effects, 0, test_effect() ), Err( Errno::ENOSPC ) );
+    assert!( effects.is_empty() );
+
+    // An effect of an unknown kind is rejected without reaching the physical device.
+    assert_eq!( forward_upload( &target, &mut effects, 0, None ), Err( Errno::EINVAL ) );
+    assert_eq!( target.uploads.borrow().len(), 1 );
+
+    let target = TestTarget { errno: Some( Errno::ENODEV ), ..TestTarget::default() };
+    effects.insert( 1, ForceFeedbackEffectId( 4 ) );
+    assert_eq!( forward_erase( &target, &mut effects, 1 ), Err( Errno::ENODEV ) );
+    assert!( effects.is_empty() );
+}
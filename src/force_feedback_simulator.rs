@@ -0,0 +1,567 @@
+use {
+    std::{
+        collections::{
+            HashMap
+        },
+        time::{
+            Duration,
+            Instant
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        force_feedback::{
+            ForceFeedbackDuration,
+            ForceFeedbackEffect,
+            ForceFeedbackEffectKind,
+            ForceFeedbackEnvelope
+        },
+        input_sys::{
+            ForceFeedbackWaveform,
+            Key
+        },
+        uinput::{
+            ForceFeedbackRequest
+        }
+    }
+};
+
+/// What the motors of a simulated device should be doing at a given moment.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ForceFeedbackOutput {
+    /// The magnitude of the strong (low frequency) rumble motor, from `0.0` to `1.0`.
+    pub strong_magnitude: f32,
+    /// The magnitude of the weak (high frequency) rumble motor, from `0.0` to `1.0`.
+    pub weak_magnitude: f32,
+    /// The horizontal component of the combined constant force, from `-1.0` (left) to `1.0` (right).
+    pub force_x: f32,
+    /// The vertical component of the combined constant force, from `-1.0` (up) to `1.0` (down).
+    pub force_y: f32
+}
+
+#[derive(Clone, Debug)]
+struct Playback {
+    started_at: Instant,
+    cycle_count: u32
+}
+
+#[derive(Clone, Debug)]
+struct SimulatedEffect {
+    effect: ForceFeedbackEffect,
+    playback: Option< Playback >,
+    last_triggered_at: Option< Instant >
+}
+
+/// A software force feedback engine.
+///
+/// It keeps track of the effects uploaded into a `VirtualDevice` and
+/// computes what the motors of a real device would be doing.
+///
+/// The effects are combined in the same way the kernel does it for
+/// simple rumble-only devices: rumble and periodic effects drive the
+/// rumble motors, while constant and ramp effects are combined into
+/// a single directional force. Condition effects depend on the position
+/// of the device's axes and are not simulated.
+pub struct ForceFeedbackSimulator {
+    effects: HashMap< u16, SimulatedEffect >,
+    max_effects: usize,
+    gain: f32
+}
+
+impl ForceFeedbackSimulator {
+    /// Creates a new simulator which can hold at most `max_effects` effects at the same time.
+    pub fn new( max_effects: usize ) -> Self {
+        ForceFeedbackSimulator {
+            effects: HashMap::new(),
+            max_effects,
+            gain: 1.0
+        }
+    }
+
+    /// Processes a request received from `VirtualDevice::poll_force_feedback`.
+    ///
+    /// Uploads and erasures are completed automatically.
+    pub fn handle( &mut self, request: ForceFeedbackRequest, now: Instant ) -> Result< (), Error > {
+        match request {
+            ForceFeedbackRequest::Upload( upload ) => {
+                match upload.effect() {
+                    None => upload.fail( nix::errno::Errno::EINVAL ),
+                    Some( effect ) => match self.upload( effect ) {
+                        Some( _ ) => upload.complete(),
+                        None => upload.fail( nix::errno::Errno::ENOSPC )
+                    }
+                }
+            },
+            ForceFeedbackRequest::Erase( erase ) => {
+                self.erase( erase.effect_id() );
+                erase.complete()
+            },
+            ForceFeedbackRequest::Enable { effect_id, cycle_count } => {
+                self.enable( effect_id, cycle_count, now );
+                Ok(())
+            },
+            ForceFeedbackRequest::Disable { effect_id } => {
+                self.disable( effect_id );
+                Ok(())
+            },
+            ForceFeedbackRequest::Gain { gain } => {
+                self.set_gain( gain );
+                Ok(())
+            },
+            ForceFeedbackRequest::Autocenter { .. } |
+            ForceFeedbackRequest::Other { .. } => Ok(())
+        }
+    }
+
+    /// Stores an effect, replacing any effect with the same ID.
+    ///
+    /// If the effect's ID is negative a free ID is assigned. Returns the ID
+    /// of the effect, or `None` if there is no space for any more effects.
+    pub fn upload( &mut self, mut effect: ForceFeedbackEffect ) -> Option< u16 > {
+        if effect.id < 0 {
+            effect.id = (0..self.max_effects).find( |id| !self.effects.contains_key( &(*id as u16) ) )? as i16;
+        }
+
+        let id = effect.id as u16;
+        if id as usize >= self.max_effects {
+            return None;
+        }
+
+        match self.effects.get_mut( &id ) {
+            Some( simulated ) => simulated.effect = effect,
+            None => {
+                self.effects.insert( id, SimulatedEffect {
+                    effect,
+                    playback: None,
+                    last_triggered_at: None
+                });
+            }
+        }
+
+        Some( id )
+    }
+
+    pub fn erase( &mut self, effect_id: u16 ) {
+        self.effects.remove( &effect_id );
+    }
+
+    /// Starts playing an effect `cycle_count` times; a `cycle_count` of zero stops it.
+    pub fn enable( &mut self, effect_id: u16, cycle_count: i32, now: Instant ) {
+        if cycle_count <= 0 {
+            self.disable( effect_id );
+            return;
+        }
+
+        if let Some( simulated ) = self.effects.get_mut( &effect_id ) {
+            simulated.playback = Some( Playback {
+                started_at: now,
+                cycle_count: cycle_count as u32
+            });
+        }
+    }
+
+    pub fn disable( &mut self, effect_id: u16 ) {
+        if let Some( simulated ) = self.effects.get_mut( &effect_id ) {
+            simulated.playback = None;
+        }
+    }
+
+    /// Sets the global gain, from `0.0` to `1.0`.
+    pub fn set_gain( &mut self, gain: f32 ) {
+        self.gain = gain.clamp( 0.0, 1.0 );
+    }
+
+    pub fn gain( &self ) -> f32 {
+        self.gain
+    }
+
+    /// Notifies the simulator that a given button was pressed,
+    /// which starts every effect triggered by that button.
+    pub fn press( &mut self, key: Key, now: Instant ) {
+        for simulated in self.effects.values_mut() {
+            if simulated.effect.trigger.button != Some( key ) {
+                continue;
+            }
+
+            let interval = simulated.effect.trigger.interval;
+            if let Some( last_triggered_at ) = simulated.last_triggered_at {
+                if now < last_triggered_at + interval {
+                    continue;
+                }
+            }
+
+            simulated.last_triggered_at = Some( now );
+            simulated.playback = Some( Playback {
+                started_at: now,
+                cycle_count: 1
+            });
+        }
+    }
+
+    /// Checks whether a given effect is producing any output at the given time.
+    ///
+    /// An effect which is waiting for its delay to pass is not considered to be playing.
+    pub fn is_playing( &self, effect_id: u16, now: Instant ) -> bool {
+        self.effects.get( &effect_id ).and_then( |simulated| elapsed_in_cycle( simulated, now ) ).is_some()
+    }
+
+    /// Computes the combined output of every effect which is playing at the given time.
+    pub fn sample( &self, now: Instant ) -> ForceFeedbackOutput {
+        let mut strong = 0.0;
+        let mut weak = 0.0;
+        let mut force_x = 0.0;
+        let mut force_y = 0.0;
+
+        for simulated in self.effects.values() {
+            let elapsed = match elapsed_in_cycle( simulated, now ) {
+                Some( elapsed ) => elapsed,
+                None => continue
+            };
+
+            let effect = &simulated.effect;
+            let length = match effect.duration {
+                ForceFeedbackDuration::Finite( length ) => Some( length ),
+                ForceFeedbackDuration::Infinite => None
+            };
+
+            let level = match effect.kind {
+                ForceFeedbackEffectKind::Rumble { strong_magnitude, weak_magnitude } => {
+                    strong += strong_magnitude as f32 / 65535.0;
+                    weak += weak_magnitude as f32 / 65535.0;
+                    continue;
+                },
+                ForceFeedbackEffectKind::Periodic { waveform, period, magnitude, offset, phase, envelope, .. } => {
+                    // Like the kernel's memless devices we drive both motors with the periodic effect.
+                    let magnitude = apply_envelope( magnitude as f32 / 32767.0, &envelope, elapsed, length );
+                    let level = offset as f32 / 32767.0 + magnitude * periodic_wave( waveform, period, phase, elapsed );
+                    strong += level.abs();
+                    weak += level.abs();
+                    continue;
+                },
+                ForceFeedbackEffectKind::Constant { level, envelope } => {
+                    apply_envelope( level as f32 / 32767.0, &envelope, elapsed, length )
+                },
+                ForceFeedbackEffectKind::Ramp { start_level, end_level, envelope } => {
+                    let progress = match length {
+                        Some( length ) if length > Duration::from_millis( 0 ) => elapsed.as_secs_f32() / length.as_secs_f32(),
+                        _ => 0.0
+                    };
+
+                    let level = start_level as f32 + (end_level as f32 - start_level as f32) * progress;
+                    apply_envelope( level / 32767.0, &envelope, elapsed, length )
+                },
+                ForceFeedbackEffectKind::Spring { .. } |
+                ForceFeedbackEffectKind::Friction { .. } |
+                ForceFeedbackEffectKind::Damper { .. } |
+                ForceFeedbackEffectKind::Inertia { .. } => continue
+            };
+
+            // A direction of 0 points down, 0x4000 points left, 0x8000 up and 0xC000 right.
+            let angle = effect.direction as f32 / 65536.0 * std::f32::consts::PI * 2.0;
+            force_x -= angle.sin() * level;
+            force_y += angle.cos() * level;
+        }
+
+        ForceFeedbackOutput {
+            strong_magnitude: (strong * self.gain).clamp( 0.0, 1.0 ),
+            weak_magnitude: (weak * self.gain).clamp( 0.0, 1.0 ),
+            force_x: (force_x * self.gain).clamp( -1.0, 1.0 ),
+            force_y: (force_y * self.gain).clamp( -1.0, 1.0 )
+        }
+    }
+}
+
+/// Returns how long the effect has been playing in its current cycle,
+/// or `None` if it's not playing at the given time.
+fn elapsed_in_cycle( simulated: &SimulatedEffect, now: Instant ) -> Option< Duration > {
+    let playback = simulated.playback.as_ref()?;
+    let elapsed = now.checked_duration_since( playback.started_at )?;
+    let delay = simulated.effect.delay;
+
+    match simulated.effect.duration {
+        ForceFeedbackDuration::Infinite => elapsed.checked_sub( delay ),
+        ForceFeedbackDuration::Finite( length ) => {
+            let cycle_length = (delay + length).as_micros();
+            if cycle_length == 0 {
+                return None;
+            }
+
+            let elapsed = elapsed.as_micros();
+            if elapsed / cycle_length >= playback.cycle_count as u128 {
+                return None;
+            }
+
+            let elapsed = Duration::from_micros( (elapsed % cycle_length) as u64 );
+            elapsed.checked_sub( delay )
+        }
+    }
+}
+
+fn apply_envelope( level: f32, envelope: &ForceFeedbackEnvelope, elapsed: Duration, length: Option< Duration > ) -> f32 {
+    let magnitude = level.abs();
+    let (envelope_level, time_from_level, time_of_envelope) = if elapsed < envelope.attack_length {
+        (envelope.attack_level, elapsed, envelope.attack_length)
+    } else {
+        match length {
+            Some( length ) if envelope.fade_length > Duration::from_millis( 0 ) && elapsed + envelope.fade_length > length => {
+                let remaining = length.checked_sub( elapsed ).unwrap_or_default();
+                (envelope.fade_level, remaining, envelope.fade_length)
+            },
+            _ => return level
+        }
+    };
+
+    // The envelope levels use the same 0x7fff scale as the effects themselves.
+    let envelope_level = envelope_level as f32 / 32767.0;
+    let progress = time_from_level.as_secs_f32() / time_of_envelope.as_secs_f32();
+    let magnitude = envelope_level + (magnitude - envelope_level) * progress;
+    magnitude.copysign( level )
+}
+
+/// Returns the value of a periodic waveform, from `-1.0` to `1.0`, at the given time.
+///
+/// The `phase` is treated as a fraction of the period, with 0x10000 being a whole period.
+/// The samples of a `ForceFeedbackWaveform::Custom` are never available to us,
+/// so such effects (and unknown waveforms) are simulated as a constant wave.
+fn periodic_wave( waveform: ForceFeedbackWaveform, period: Duration, phase: u16, elapsed: Duration ) -> f32 {
+    let period = period.as_micros();
+    if period == 0 {
+        return 1.0;
+    }
+
+    let shift = period * phase as u128 / 0x10000;
+    let t = ((elapsed.as_micros() + shift) % period) as f32 / period as f32;
+    match waveform {
+        ForceFeedbackWaveform::Square => if t < 0.5 { 1.0 } else { -1.0 },
+        ForceFeedbackWaveform::Triangle => 1.0 - 4.0 * (t - 0.5).abs(),
+        ForceFeedbackWaveform::Sine => (t * std::f32::consts::PI * 2.0).sin(),
+        ForceFeedbackWaveform::SawUp => 2.0 * t - 1.0,
+        ForceFeedbackWaveform::SawDown => 1.0 - 2.0 * t,
+        ForceFeedbackWaveform::Custom |
+        ForceFeedbackWaveform::Other( _ ) => 1.0
+    }
+}
+
+#[cfg(test)]
+fn test_effect( id: i16, kind: ForceFeedbackEffectKind, duration: ForceFeedbackDuration, delay: Duration ) -> ForceFeedbackEffect {
+    ForceFeedbackEffect {
+        id,
+        direction: 0,
+        kind,
+        duration,
+        delay,
+        trigger: crate::force_feedback::ForceFeedbackTrigger::default()
+    }
+}
+
+#[cfg(test)]
+fn rumble( strong_magnitude: u16, weak_magnitude: u16 ) -> ForceFeedbackEffectKind {
+    ForceFeedbackEffectKind::Rumble { strong_magnitude, weak_magnitude }
+}
+
+#[test]
+fn test_simulator_rumble_with_delay_and_length() {
+    let start = Instant::now();
+    let ms = |value| start + Duration::from_millis( value );
+
+    let mut simulator = ForceFeedbackSimulator::new( 4 );
+    let effect = test_effect( -1, rumble( 0xffff, 0x8000 ), ForceFeedbackDuration::Finite( Duration::from_millis( 100 ) ), Duration::from_millis( 50 ) );
+    let id = simulator.upload( effect ).unwrap();
+    assert_eq!( simulator.sample( ms( 0 ) ), ForceFeedbackOutput::default() );
+
+    simulator.enable( id, 1, start );
+    assert!( !simulator.is_playing( id, ms( 10 ) ) );
+    assert_eq!( simulator.sample( ms( 10 ) ), ForceFeedbackOutput::default() );
+
+    assert!( simulator.is_playing( id, ms( 60 ) ) );
+    let output = simulator.sample( ms( 60 ) );
+    assert_eq!( output.strong_magnitude, 1.0 );
+    assert!( (output.weak_magnitude - 0.5).abs() < 0.001 );
+
+    assert!( !simulator.is_playing( id, ms( 160 ) ) );
+}
+
+#[test]
+fn test_simulator_cycle_count() {
+    let start = Instant::now();
+    let ms = |value| start + Duration::from_millis( value );
+
+    let mut simulator = ForceFeedbackSimulator::new( 4 );
+    let effect = test_effect( 0, rumble( 0xffff, 0 ), ForceFeedbackDuration::Finite( Duration::from_millis( 100 ) ), Duration::from_millis( 100 ) );
+    let id = simulator.upload( effect ).unwrap();
+    simulator.enable( id, 2, start );
+
+    assert!( !simulator.is_playing( id, ms( 50 ) ) );
+    assert!( simulator.is_playing( id, ms( 150 ) ) );
+    assert!( !simulator.is_playing( id, ms( 250 ) ) );
+    assert!( simulator.is_playing( id, ms( 350 ) ) );
+    assert!( !simulator.is_playing( id, ms( 450 ) ) );
+    assert!( !simulator.is_playing( id, ms( 550 ) ) );
+
+    simulator.enable( id, 1, start );
+    simulator.disable( id );
+    assert!( !simulator.is_playing( id, ms( 150 ) ) );
+}
+
+#[test]
+fn test_simulator_gain_and_combination() {
+    let start = Instant::now();
+    let mut simulator = ForceFeedbackSimulator::new( 4 );
+    let first = simulator.upload( test_effect( -1, rumble( 0x8000, 0 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) ) ).unwrap();
+    let second = simulator.upload( test_effect( -1, rumble( 0xc000, 0x4000 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) ) ).unwrap();
+    assert_ne!( first, second );
+
+    simulator.enable( first, 1, start );
+    simulator.enable( second, 1, start );
+
+    let output = simulator.sample( start );
+    assert_eq!( output.strong_magnitude, 1.0 );
+    assert!( (output.weak_magnitude - 0.25).abs() < 0.001 );
+
+    simulator.set_gain( 0.5 );
+    let output = simulator.sample( start );
+    assert!( (output.weak_magnitude - 0.125).abs() < 0.001 );
+}
+
+#[test]
+fn test_simulator_max_effects() {
+    let mut simulator = ForceFeedbackSimulator::new( 1 );
+    let effect = test_effect( -1, rumble( 0, 0 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) );
+    assert_eq!( simulator.upload( effect.clone() ), Some( 0 ) );
+    assert_eq!( simulator.upload( effect.clone() ), None );
+
+    let mut effect = effect;
+    effect.id = 0;
+    assert_eq!( simulator.upload( effect.clone() ), Some( 0 ) );
+
+    effect.id = 1;
+    assert_eq!( simulator.upload( effect ), None );
+}
+
+#[test]
+fn test_simulator_constant_force_with_envelope() {
+    let start = Instant::now();
+    let ms = |value| start + Duration::from_millis( value );
+
+    let mut simulator = ForceFeedbackSimulator::new( 1 );
+    let mut effect = test_effect( -1, ForceFeedbackEffectKind::Constant {
+        level: 0x7fff,
+        envelope: ForceFeedbackEnvelope {
+            attack_length: Duration::from_millis( 100 ),
+            attack_level: 0,
+            fade_length: Duration::from_millis( 100 ),
+            fade_level: 0
+        }
+    }, ForceFeedbackDuration::Finite( Duration::from_millis( 400 ) ), Duration::from_millis( 0 ) );
+
+    // Pointing up.
+    effect.direction = 0x8000;
+
+    let id = simulator.upload( effect ).unwrap();
+    simulator.enable( id, 1, start );
+
+    let output = simulator.sample( ms( 50 ) );
+    assert!( output.force_x.abs() < 0.001 );
+    assert!( (output.force_y + 0.5).abs() < 0.001 );
+
+    let output = simulator.sample( ms( 200 ) );
+    assert!( (output.force_y + 1.0).abs() < 0.001 );
+
+    let output = simulator.sample( ms( 375 ) );
+    assert!( (output.force_y + 0.25).abs() < 0.001 );
+}
+
+#[test]
+fn test_simulator_trigger() {
+    let start = Instant::now();
+    let ms = |value| start + Duration::from_millis( value );
+
+    let mut simulator = ForceFeedbackSimulator::new( 1 );
+    let mut effect = test_effect( -1, rumble( 0xffff, 0xffff ), ForceFeedbackDuration::Finite( Duration::from_millis( 100 ) ), Duration::from_millis( 0 ) );
+    effect.trigger.button = Some( Key::PadSouth );
+    effect.trigger.interval = Duration::from_millis( 500 );
+    let id = simulator.upload( effect ).unwrap();
+
+    simulator.press( Key::PadNorth, start );
+    assert!( !simulator.is_playing( id, ms( 10 ) ) );
+
+    simulator.press( Key::PadSouth, start );
+    assert!( simulator.is_playing( id, ms( 10 ) ) );
+    assert!( !simulator.is_playing( id, ms( 200 ) ) );
+
+    simulator.press( Key::PadSouth, ms( 200 ) );
+    assert!( !simulator.is_playing( id, ms( 210 ) ) );
+
+    simulator.press( Key::PadSouth, ms( 600 ) );
+    assert!( simulator.is_playing( id, ms( 610 ) ) );
+}
+
+#[test]
+fn test_simulator_envelope_attack_level() {
+    let start = Instant::now();
+    let ms = |value| start + Duration::from_millis( value );
+
+    let mut simulator = ForceFeedbackSimulator::new( 1 );
+    let effect = test_effect( -1, ForceFeedbackEffectKind::Constant {
+        level: 0x7fff,
+        envelope: ForceFeedbackEnvelope {
+            attack_length: Duration::from_millis( 100 ),
+            attack_level: 0x4000,
+            fade_length: Duration::from_millis( 0 ),
+            fade_level: 0
+        }
+    }, ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) );
+
+    let id = simulator.upload( effect ).unwrap();
+    simulator.enable( id, 1, start );
+
+    // A direction of 0 points down.
+    let output = simulator.sample( ms( 0 ) );
+    assert!( (output.force_y - 0.5).abs() < 0.001 );
+
+    let output = simulator.sample( ms( 50 ) );
+    assert!( (output.force_y - 0.75).abs() < 0.001 );
+
+    let output = simulator.sample( ms( 150 ) );
+    assert!( (output.force_y - 1.0).abs() < 0.001 );
+}
+
+#[test]
+fn test_simulator_periodic_waveform() {
+    let start = Instant::now();
+    let ms = |value| start + Duration::from_millis( value );
+    let periodic = |waveform, offset, phase| ForceFeedbackEffectKind::Periodic {
+        waveform,
+        period: Duration::from_millis( 100 ),
+        magnitude: 0x4000,
+        offset,
+        phase,
+        envelope: ForceFeedbackEnvelope::default(),
+        custom_data: Vec::new()
+    };
+
+    let mut simulator = ForceFeedbackSimulator::new( 1 );
+    let effect = test_effect( -1, periodic( ForceFeedbackWaveform::Square, 0, 0 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) );
+    let id = simulator.upload( effect ).unwrap();
+    simulator.enable( id, 1, start );
+    assert!( (simulator.sample( ms( 25 ) ).strong_magnitude - 0.5).abs() < 0.001 );
+    assert!( (simulator.sample( ms( 75 ) ).strong_magnitude - 0.5).abs() < 0.001 );
+
+    // With an offset the troughs of the wave are weaker than its peaks.
+    let effect = test_effect( id as i16, periodic( ForceFeedbackWaveform::Square, 0x2000, 0 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) );
+    simulator.upload( effect ).unwrap();
+    assert!( (simulator.sample( ms( 25 ) ).strong_magnitude - 0.75).abs() < 0.001 );
+    assert!( (simulator.sample( ms( 75 ) ).strong_magnitude - 0.25).abs() < 0.001 );
+
+    // A sine wave crosses zero every half a period; a quarter period of phase shifts it to the peaks.
+    let effect = test_effect( id as i16, periodic( ForceFeedbackWaveform::Sine, 0, 0 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) );
+    simulator.upload( effect ).unwrap();
+    assert!( simulator.sample( ms( 50 ) ).strong_magnitude < 0.001 );
+    assert!( (simulator.sample( ms( 25 ) ).strong_magnitude - 0.5).abs() < 0.001 );
+
+    let effect = test_effect( id as i16, periodic( ForceFeedbackWaveform::Sine, 0, 0x4000 ), ForceFeedbackDuration::Infinite, Duration::from_millis( 0 ) );
+    simulator.upload( effect ).unwrap();
+    assert!( (simulator.sample( ms( 50 ) ).strong_magnitude - 0.5).abs() < 0.001 );
+}
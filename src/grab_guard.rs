@@ -0,0 +1,67 @@
+use {
+    std::{
+        io,
+        thread,
+        time::{
+            Duration,
+            Instant
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            Device
+        }
+    }
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis( 10 );
+
+/// Keeps a device grabbed for exclusive access, and releases it when dropped.
+pub struct GrabGuard< 'a > {
+    device: &'a Device
+}
+
+impl< 'a > GrabGuard< 'a > {
+    /// Grabs the device immediately.
+    pub fn new( device: &'a Device ) -> Result< Self, Error > {
+        device.grab()?;
+        Ok( GrabGuard { device } )
+    }
+
+    /// Waits until none of the device's keys are held down, and then grabs it.
+    ///
+    /// Grabbing a device while a key is held, e.g. the Enter with which
+    /// the program was started from a terminal, hides the release from everyone
+    /// else, who will then think that the key is held forever.
+    ///
+    /// Fails with `ErrorKind::TimedOut` if keys are still held after `timeout`;
+    /// with `None` it waits indefinitely.
+    pub fn after_keys_are_released( device: &'a Device, timeout: Option< Duration > ) -> Result< Self, Error > {
+        let deadline = timeout.map( |timeout| Instant::now() + timeout );
+        while device.pressed_keys()?.next().is_some() {
+            if let Some( deadline ) = deadline {
+                if Instant::now() >= deadline {
+                    let error = io::Error::new( io::ErrorKind::TimedOut, "keys are still held down" );
+                    return Err( Error::from_io( "wait for the keys to be released", error ) );
+                }
+            }
+
+            thread::sleep( POLL_INTERVAL );
+        }
+
+        GrabGuard::new( device )
+    }
+
+    pub fn device( &self ) -> &'a Device {
+        self.device
+    }
+}
+
+impl< 'a > Drop for GrabGuard< 'a > {
+    fn drop( &mut self ) {
+        let _ = self.device.release();
+    }
+}
@@ -5,9 +5,6 @@ use {
             self,
             File
         },
-        io::{
-            self
-        },
         os::{
             unix::{
                 io::{
@@ -25,30 +22,52 @@ use {
             Path
         },
         slice,
+        sync::{
+            atomic::{
+                AtomicBool,
+                Ordering
+            }
+        },
         time::{
-            Duration
+            Duration,
+            Instant
         }
     },
     crate::{
+        calibration::{
+            AbsoluteAxisCalibration
+        },
+        error::{
+            Context,
+            Error
+        },
         event_bits_iter::{
             EventBitsIter
         },
+        force_feedback::{
+            Effect,
+            ForceFeedbackCapabilities,
+            ForceFeedbackEffect,
+            ForceFeedbackEffectId,
+            fraction_to_raw
+        },
         input_sys::{
             self,
             AbsoluteAxis,
             Bus,
             EventKind,
             ForceFeedback,
+            InputProperty,
             Key,
+            Led,
+            Misc,
             RawAbsInfo,
             RawDeviceId,
-            RawForceFeedbackBody,
-            RawForceFeedbackEffect,
-            RawForceFeedbackReplay,
-            RawForceFeedbackRumbleEffect,
-            RawForceFeedbackTrigger,
             RawInputEvent,
+            RawInputMask,
             RelativeAxis,
+            Sound,
+            Switch,
             Timestamp
         },
         utils::{
@@ -88,6 +107,11 @@ pub enum InputEventBody {
     },
     Flush,
     Dropped,
+    /// Reports whether a force feedback effect started or stopped playing.
+    EffectStatus {
+        effect_id: u16,
+        playing: bool
+    },
     Other {
         kind: EventKind,
         code: u16,
@@ -105,6 +129,9 @@ impl From< RawInputEvent > for InputEvent {
             EventKind::AbsoluteAxis => InputEventBody::AbsoluteMove { axis: raw_event.code.into(), position: raw_event.value },
             EventKind::Synchronization if raw_event.code == 0 && raw_event.value == 0 => InputEventBody::Flush,
             EventKind::Synchronization if raw_event.code == 3 && raw_event.value == 0 => InputEventBody::Dropped,
+            EventKind::ForceFeedbackStatus if raw_event.value == input_sys::FF_STATUS_STOPPED || raw_event.value == input_sys::FF_STATUS_PLAYING => {
+                InputEventBody::EffectStatus { effect_id: raw_event.code, playing: raw_event.value == input_sys::FF_STATUS_PLAYING }
+            },
             _ => InputEventBody::Other{
                 kind: raw_event.kind.into(),
                 code: raw_event.code,
@@ -128,6 +155,9 @@ impl From< InputEvent > for RawInputEvent {
             InputEventBody::AbsoluteMove { axis, position } => (EventKind::AbsoluteAxis, axis.into(), position),
             InputEventBody::Flush => (EventKind::Synchronization, 0, 0),
             InputEventBody::Dropped => (EventKind::Synchronization, 3, 0),
+            InputEventBody::EffectStatus { effect_id, playing } => {
+                (EventKind::ForceFeedbackStatus, effect_id, if playing { input_sys::FF_STATUS_PLAYING } else { input_sys::FF_STATUS_STOPPED })
+            },
             InputEventBody::Other { kind, code, value } => (kind, code, value)
         };
 
@@ -162,6 +192,11 @@ pub trait EventCode: From< u16 > {
     const EVENT_KIND: EventKind;
 }
 
+// The kernel exposes the bitmap of event kinds under the `EV_SYN` kind.
+impl EventCode for EventKind {
+    const EVENT_KIND: EventKind = EventKind::Synchronization;
+}
+
 impl EventCode for Key {
     const EVENT_KIND: EventKind = EventKind::Key;
 }
@@ -174,6 +209,22 @@ impl EventCode for AbsoluteAxis {
     const EVENT_KIND: EventKind = EventKind::AbsoluteAxis;
 }
 
+impl EventCode for Misc {
+    const EVENT_KIND: EventKind = EventKind::Misc;
+}
+
+impl EventCode for Switch {
+    const EVENT_KIND: EventKind = EventKind::Switch;
+}
+
+impl EventCode for Led {
+    const EVENT_KIND: EventKind = EventKind::LED;
+}
+
+impl EventCode for Sound {
+    const EVENT_KIND: EventKind = EventKind::Sound;
+}
+
 impl EventCode for ForceFeedback {
     const EVENT_KIND: EventKind = EventKind::ForceFeedback;
 }
@@ -208,145 +259,118 @@ impl From< DeviceId > for RawDeviceId {
     }
 }
 
-pub(crate) fn emit_into< T >( fp: &File, body: T ) -> Result< (), io::Error > where T: AsRef< InputEventBody > {
-    let raw_event: RawInputEvent = InputEvent {
-        timestamp: Timestamp {
-            sec: 0,
-            usec: 0
-        },
-        body: body.as_ref().clone()
-    }.into();
-
-    let bytes = &raw_event as *const RawInputEvent as *const u8;
-    let bytes = unsafe { slice::from_raw_parts( bytes, mem::size_of::< RawInputEvent >() ) };
+fn write_raw_events( fp: &File, events: &[RawInputEvent], operation: &'static str ) -> Result< (), Error > {
+    let bytes = events.as_ptr() as *const u8;
+    let bytes = unsafe { slice::from_raw_parts( bytes, mem::size_of_val( events ) ) };
     let result = unsafe { libc::write( fp.as_raw_fd(), bytes.as_ptr() as *const libc::c_void, bytes.len() ) };
     if result < 0 {
-        return Err( io::Error::last_os_error() );
+        return Err( Error::last_os_error( operation ) );
     }
 
     let count = result as usize;
-    assert_eq!( count, bytes.len() );
+    if count != bytes.len() {
+        return Err( Error::other( operation, format!( "short write of {} bytes out of {}", count, bytes.len() ) ) );
+    }
 
     Ok(())
 }
 
-#[derive(Clone, Debug)]
-pub struct AbsoluteAxisBit {
-    pub axis: AbsoluteAxis,
-    pub initial_value: i32,
-    pub minimum: i32,
-    pub maximum: i32,
-    pub noise_threshold: i32,
-    pub deadzone: i32,
-    pub resolution: i32
+fn to_raw_event( body: &InputEventBody ) -> RawInputEvent {
+    InputEvent {
+        timestamp: Timestamp {
+            sec: 0,
+            usec: 0
+        },
+        body: body.clone()
+    }.into()
 }
 
-#[derive(Clone, Debug)]
-pub enum EventBit {
-    Key( Key ),
-    RelativeAxis( RelativeAxis ),
-    AbsoluteAxis( AbsoluteAxisBit ),
-    ForceFeedback( ForceFeedback )
+pub(crate) fn emit_into< T >( fp: &File, body: T ) -> Result< (), Error > where T: AsRef< InputEventBody > {
+    write_raw_events( fp, &[ to_raw_event( body.as_ref() ) ], "emit an event" )
 }
 
-#[derive(Clone, Debug)]
-pub enum ForceFeedbackEffectKind {
-    Rumble {
-        strong_magnitude: u16,
-        weak_magnitude: u16
+/// Emits the events followed by an `InputEventBody::Flush` with a single `write`.
+pub(crate) fn emit_frame_into( fp: &File, bodies: &[InputEventBody] ) -> Result< (), Error > {
+    let mut events: Vec< _ > = bodies.iter().map( to_raw_event ).collect();
+    if bodies.last() != Some( &InputEventBody::Flush ) {
+        events.push( to_raw_event( &InputEventBody::Flush ) );
     }
-}
 
-impl ForceFeedbackEffectKind {
-    unsafe fn from_raw( kind: u16, body: &RawForceFeedbackBody ) -> Self {
-        match kind {
-            crate::input_sys::FF_RUMBLE => {
-                let raw_effect = &body.rumble;
-                ForceFeedbackEffectKind::Rumble {
-                    strong_magnitude: raw_effect.strong_magnitude,
-                    weak_magnitude: raw_effect.weak_magnitude
-                }
-            },
-            kind => unimplemented!( "unsupported force feedback effect: {}", kind )
-        }
-    }
+    write_raw_events( fp, &events, "emit a frame of events" )
 }
 
-#[derive(Clone, Debug)]
-pub enum ForceFeedbackDuration {
-    Finite( std::time::Duration ),
-    Infinite
+/// Parameters of an absolute axis.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AbsInfo {
+    /// Latest reported value for the axis.
+    pub value: i32,
+    /// Minimum value for the axis.
+    pub minimum: i32,
+    /// Maximum value for the axis.
+    pub maximum: i32,
+    /// Threshold for noise filtering. (Also known as `fuzz`.)
+    pub noise_threshold: i32,
+    /// Values within this distance from the center are reported as the center. (Also known as `flat`.)
+    pub deadzone: i32,
+    /// Resolution of the reported values, in units per millimeter (or units per radian for rotational axes).
+    pub resolution: i32
 }
 
-#[derive(Clone, Debug)]
-pub struct ForceFeedbackEffect {
-    pub id: i16,
-    pub direction: u16,
-    pub kind: ForceFeedbackEffectKind,
-    pub duration: ForceFeedbackDuration,
-    pub delay: std::time::Duration,
-    pub trigger: RawForceFeedbackTrigger
+impl From< RawAbsInfo > for AbsInfo {
+    fn from( info: RawAbsInfo ) -> Self {
+        AbsInfo {
+            value: info.value,
+            minimum: info.minimum,
+            maximum: info.maximum,
+            noise_threshold: info.noise_threshold,
+            deadzone: info.deadzone,
+            resolution: info.resolution
+        }
+    }
 }
 
-impl ForceFeedbackEffect {
-    pub(crate) unsafe fn from_raw( raw_effect: &RawForceFeedbackEffect ) -> Self {
-        ForceFeedbackEffect {
-            id: raw_effect.id,
-            direction: raw_effect.direction,
-            kind: ForceFeedbackEffectKind::from_raw( raw_effect.kind, &raw_effect.body ),
-            trigger: raw_effect.trigger,
-            duration: match raw_effect.replay.length {
-                0 => ForceFeedbackDuration::Infinite,
-                length => ForceFeedbackDuration::Finite( std::time::Duration::from_millis( length as u64 ) )
-            },
-            delay: std::time::Duration::from_millis( raw_effect.replay.delay as u64 )
+impl From< AbsInfo > for RawAbsInfo {
+    fn from( info: AbsInfo ) -> Self {
+        RawAbsInfo {
+            value: info.value,
+            minimum: info.minimum,
+            maximum: info.maximum,
+            noise_threshold: info.noise_threshold,
+            deadzone: info.deadzone,
+            resolution: info.resolution
         }
     }
 }
 
-fn convert_and_clip( duration: std::time::Duration ) -> u16 {
-    let duration = duration.as_millis();
-    if duration > 0x7fff {
-        0x7fff
-    } else {
-        duration as u16
-    }
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AbsoluteAxisBit {
+    pub axis: AbsoluteAxis,
+    pub initial_value: i32,
+    pub minimum: i32,
+    pub maximum: i32,
+    pub noise_threshold: i32,
+    pub deadzone: i32,
+    pub resolution: i32
 }
 
-impl From< ForceFeedbackEffect > for RawForceFeedbackEffect {
-    fn from( effect: ForceFeedbackEffect ) -> Self {
-        RawForceFeedbackEffect {
-            id: effect.id,
-            direction: effect.direction,
-            trigger: effect.trigger,
-            replay: RawForceFeedbackReplay {
-                length: match effect.duration {
-                    ForceFeedbackDuration::Finite( duration ) => convert_and_clip( duration ),
-                    ForceFeedbackDuration::Infinite => 0
-                },
-                delay: convert_and_clip( effect.delay )
-            },
-            body: match effect.kind {
-                ForceFeedbackEffectKind::Rumble { weak_magnitude, strong_magnitude } => {
-                    RawForceFeedbackBody {
-                        rumble: RawForceFeedbackRumbleEffect {
-                            weak_magnitude, strong_magnitude
-                        }
-                    }
-                }
-            },
-            kind: match effect.kind {
-                ForceFeedbackEffectKind::Rumble { .. } => crate::input_sys::FF_RUMBLE
-            }
-        }
-    }
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EventBit {
+    Key( Key ),
+    RelativeAxis( RelativeAxis ),
+    AbsoluteAxis( AbsoluteAxisBit ),
+    Misc( Misc ),
+    Switch( Switch ),
+    Led( Led ),
+    Sound( Sound ),
+    ForceFeedback( ForceFeedback )
 }
 
 pub struct Device {
-    fp: File
+    fp: File,
+    is_revoked: AtomicBool
 }
 
-pub fn poll_read( fd: std::os::unix::io::RawFd, timeout: Option< Duration > ) -> Result< bool, io::Error > {
+pub fn poll_read( fd: std::os::unix::io::RawFd, timeout: Option< Duration > ) -> Result< bool, Error > {
     let timeout = timeout.map( |timeout| {
         libc::timespec {
             tv_sec: timeout.as_secs() as _,
@@ -363,8 +387,9 @@ pub fn poll_read( fd: std::os::unix::io::RawFd, timeout: Option< Duration > ) ->
 
     let sigmask = unsafe {
         let mut sigmask = std::mem::MaybeUninit::uninit();
-        let errcode = libc::sigemptyset( sigmask.as_mut_ptr() );
-        assert_eq!( errcode, 0 );
+        if libc::sigemptyset( sigmask.as_mut_ptr() ) != 0 {
+            return Err( Error::last_os_error( "wait for events" ) );
+        }
 
         sigmask.assume_init()
     };
@@ -374,11 +399,9 @@ pub fn poll_read( fd: std::os::unix::io::RawFd, timeout: Option< Duration > ) ->
         libc::ppoll( &mut pollfd, 1, timeout_p, &sigmask )
     };
 
-    std::mem::drop( timeout );
-
     if result < 0 {
-        let error = io::Error::last_os_error();
-        if error.kind() == io::ErrorKind::Interrupted {
+        let error = Error::last_os_error( "wait for events" );
+        if error.raw_os_error() == Some( libc::EINTR ) {
             return Ok( false );
         }
         return Err( error );
@@ -389,7 +412,7 @@ pub fn poll_read( fd: std::os::unix::io::RawFd, timeout: Option< Duration > ) ->
     Ok( pollfd.revents & (libc::POLLIN | libc::POLLHUP) != 0 )
 }
 
-pub(crate) fn read_raw_input_event( fp: &File, timeout: Option< Duration > ) -> Result< Option< RawInputEvent >, io::Error > {
+pub(crate) fn read_raw_input_event( fp: &File, timeout: Option< Duration > ) -> Result< Option< RawInputEvent >, Error > {
     if poll_read( fp.as_raw_fd(), timeout )? {
         let mut buffer = RawInputEvent::default();
         let raw_buffer = unsafe {
@@ -398,7 +421,11 @@ pub(crate) fn read_raw_input_event( fp: &File, timeout: Option< Duration > ) ->
 
         let result = unsafe { libc::read( fp.as_raw_fd(), raw_buffer.as_mut_ptr() as *mut libc::c_void, raw_buffer.len() as libc::size_t ) };
         if result < 0 {
-            return Err( io::Error::last_os_error() );
+            let error = Error::last_os_error( "read an event" );
+            if error.raw_os_error() == Some( libc::EAGAIN ) {
+                return Ok( None );
+            }
+            return Err( error );
         }
 
         let count = result as usize;
@@ -406,46 +433,43 @@ pub(crate) fn read_raw_input_event( fp: &File, timeout: Option< Duration > ) ->
             return Ok( Some( buffer ) );
         }
 
-        assert_eq!( count, 0 );
+        if count != 0 {
+            return Err( Error::other( "read an event", format!( "short read of {} bytes", count ) ) );
+        }
     }
 
     Ok( None )
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub struct ForceFeedbackEffectId( i16 );
-
 impl Device {
-    pub fn open< P >( path: P ) -> Result< Self, io::Error > where P: AsRef< Path > {
+    pub fn open< P >( path: P ) -> Result< Self, Error > where P: AsRef< Path > {
         let path = path.as_ref();
         let fp = fs::OpenOptions::new()
             .read( true )
             .write( true )
             .create( false )
-            .open( path )?;
+            .open( path )
+            .context( "open the device" )?;
 
         let flags = unsafe { libc::fcntl( fp.as_raw_fd(), libc::F_GETFL, 0 ) };
         if flags < 0 {
-            let error = io::Error::last_os_error();
-            return Err( error );
+            return Err( Error::last_os_error( "get the file descriptor flags" ) );
         }
 
         if unsafe { libc::fcntl( fp.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK ) < -1 } {
-            let error = io::Error::last_os_error();
-            return Err( error );
+            return Err( Error::last_os_error( "make the file descriptor non-blocking" ) );
         }
 
         let device = Device {
-            fp
+            fp,
+            is_revoked: AtomicBool::new( false )
         };
 
-        device.set_clock_source( libc::CLOCK_MONOTONIC )
-            .map_err( |error| io::Error::new( io::ErrorKind::Other, format!( "failed to set the clock source to CLOCK_MONOTONIC: {}", error ) ) )?;
-
+        device.set_clock_source( libc::CLOCK_MONOTONIC )?;
         Ok( device )
     }
 
-    pub fn id( &self ) -> Result< DeviceId, nix::Error > {
+    pub fn id( &self ) -> Result< DeviceId, Error > {
         let mut raw_id = RawDeviceId {
             bus: 0,
             vendor: 0,
@@ -453,54 +477,163 @@ impl Device {
             version: 0
         };
 
-        unsafe {
-            input_sys::evdev_get_id( self.fp.as_raw_fd(), &mut raw_id )?;
-        }
+        self.check( "get the device ID", unsafe {
+            input_sys::evdev_get_id( self.fp.as_raw_fd(), &mut raw_id )
+        })?;
 
         Ok( raw_id.into() )
     }
 
-    pub fn name( &self ) -> Result< String, nix::Error > {
-        unsafe {
+    pub fn name( &self ) -> Result< String, Error > {
+        self.check( "get the device name", unsafe {
             ioctl_get_string( self.fp.as_raw_fd(), b'E', 0x06 )
-        }
+        })
     }
 
-    pub fn physical_location( &self ) -> Result< String, nix::Error > {
-        unsafe {
+    pub fn physical_location( &self ) -> Result< String, Error > {
+        self.check( "get the physical location", unsafe {
             ioctl_get_string( self.fp.as_raw_fd(), b'E', 0x07 )
+        })
+    }
+
+    /// Returns the unique identifier of the device (e.g. its serial number), if it has one.
+    pub fn unique_id( &self ) -> Result< Option< String >, Error > {
+        let result = unsafe {
+            ioctl_get_string( self.fp.as_raw_fd(), b'E', 0x08 )
+        };
+
+        match result {
+            Ok( unique_id ) => Ok( Some( unique_id ).filter( |unique_id| !unique_id.is_empty() ) ),
+            // The kernel reports a missing identifier with `ENOENT`.
+            Err( nix::Error::Sys( nix::errno::Errno::ENOENT ) ) => Ok( None ),
+            Err( error ) => self.check( "get the unique ID", Err( error ) )
+        }
+    }
+
+    /// Returns the properties of the device, which describe how it should be interpreted.
+    pub fn properties( &self ) -> Result< impl FusedIterator< Item = InputProperty >, Error > {
+        let mut buffer = vec![ 0; 64 ];
+        let count = self.check( "get the device properties", unsafe {
+            input_sys::evdev_get_properties( self.fp.as_raw_fd(), buffer.as_mut_ptr(), buffer.len() )
+        })? as usize;
+        buffer.truncate( count );
+
+        Ok( EventBitsIter::< InputProperty >::new( buffer.into() ) )
+    }
+
+    /// Returns the keys and buttons which are currently held down.
+    pub fn pressed_keys( &self ) -> Result< impl FusedIterator< Item = Key >, Error > {
+        // Enough for KEY_CNT bits.
+        let mut buffer = vec![ 0; 0x300 / 8 ];
+        let count = self.check( "get the key state", unsafe {
+            input_sys::evdev_get_key_state( self.fp.as_raw_fd(), buffer.as_mut_ptr(), buffer.len() )
+        })? as usize;
+        buffer.truncate( count );
+
+        Ok( EventBitsIter::< Key >::new( buffer.into() ) )
+    }
+
+    /// Returns the delay after which held keys start repeating and the period with which they repeat,
+    /// or `None` if the device doesn't support autorepeat.
+    pub fn autorepeat( &self ) -> Result< Option< (Duration, Duration) >, Error > {
+        if !self.event_bits_of_kind::< EventKind >()?.any( |kind| kind == EventKind::AutoRepeat ) {
+            return Ok( None );
         }
+
+        let mut autorepeat = [0; 2];
+        self.check( "get the autorepeat settings", unsafe {
+            input_sys::evdev_get_autorepeat( self.fp.as_raw_fd(), &mut autorepeat )
+        })?;
+
+        Ok( Some( (Duration::from_millis( autorepeat[ 0 ] as u64 ), Duration::from_millis( autorepeat[ 1 ] as u64 )) ) )
     }
 
-    pub fn read( &self, timeout: Option< Duration > ) -> Result< Option< InputEvent >, io::Error > {
-        read_raw_input_event( &self.fp, timeout ).map( |event| event.map( |event| event.into() ) )
+    pub fn read( &self, timeout: Option< Duration > ) -> Result< Option< InputEvent >, Error > {
+        read_raw_input_event( &self.fp, timeout )
+            .map( |event| event.map( |event| event.into() ) )
+            .map_err( |error| self.check_revoked( error ) )
     }
 
-    pub fn get_raw_abs_info( &self, axis: AbsoluteAxis ) -> Result< RawAbsInfo, nix::Error > {
-        unsafe {
+    pub fn get_raw_abs_info( &self, axis: AbsoluteAxis ) -> Result< RawAbsInfo, Error > {
+        self.check( "get the absolute axis info", unsafe {
             crate::input_sys::evdev_get_abs_info( self.fp.as_raw_fd(), axis )
+        })
+    }
+
+    pub fn abs_info( &self, axis: AbsoluteAxis ) -> Result< AbsInfo, Error > {
+        self.get_raw_abs_info( axis ).map( AbsInfo::from )
+    }
+
+    /// Overrides the parameters of a given absolute axis.
+    ///
+    /// This is mostly useful to correct the ranges of devices which
+    /// report them incorrectly. The new parameters are visible to
+    /// every other reader of the device.
+    pub fn set_abs_info( &self, axis: AbsoluteAxis, info: AbsInfo ) -> Result< (), Error > {
+        if info.minimum > info.maximum || info.noise_threshold < 0 || info.deadzone < 0 || info.resolution < 0 {
+            return Err( Error::invalid_argument( "set the absolute axis info", format!( "invalid parameters for {}: {:?}", axis, info ) ) );
+        }
+
+        let info: RawAbsInfo = info.into();
+        self.check( "set the absolute axis info", unsafe {
+            crate::input_sys::evdev_set_abs_info( self.fp.as_raw_fd(), axis, &info )
+        })
+    }
+
+    /// Watches the absolute axes of the device for a given amount of time
+    /// and records how far they move.
+    ///
+    /// The user is expected to leave every axis at rest for a moment
+    /// and then move it to its extremes. Use `AbsoluteAxisCalibration::proposal` on the result
+    /// to get the corrected parameters, which can then be applied with `set_abs_info`.
+    pub fn calibrate_absolute_axes( &self, duration: Duration ) -> Result< Vec< AbsoluteAxisCalibration >, Error > {
+        let mut calibrations = Vec::new();
+        for axis in self.event_bits_of_kind::< AbsoluteAxis >()? {
+            let info = self.abs_info( axis )?;
+            calibrations.push( AbsoluteAxisCalibration::new( axis, info ) );
+        }
+
+        let deadline = Instant::now() + duration;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            let event = match self.read( Some( deadline - now ) )? {
+                Some( event ) => event,
+                None => continue
+            };
+
+            if let InputEventBody::AbsoluteMove { axis, position } = event.body {
+                if let Some( calibration ) = calibrations.iter_mut().find( |calibration| calibration.axis() == axis ) {
+                    calibration.observe( position );
+                }
+            }
         }
+
+        Ok( calibrations )
     }
 
-    fn append_event_bits_into_buffer( &self, kind: EventKind, buffer: &mut Vec< u8 > ) -> Result< usize, nix::Error > {
+    fn append_event_bits_into_buffer( &self, kind: EventKind, buffer: &mut Vec< u8 > ) -> Result< usize, Error > {
         let length = buffer.len();
         buffer.resize( length + 1024, 0 );
-        let count = unsafe {
-            crate::input_sys::evdev_get_event_bits( self.fp.as_raw_fd(), kind, buffer[ length..length + 1024 ].as_mut_ptr(), 1024 )?
-        } as usize;
+        let count = self.check( "get the event bits", unsafe {
+            crate::input_sys::evdev_get_event_bits( self.fp.as_raw_fd(), kind, buffer[ length..length + 1024 ].as_mut_ptr(), 1024 )
+        })? as usize;
         buffer.truncate( length + count );
 
         Ok( count )
     }
 
-    pub fn event_bits_of_kind< T >( &self ) -> Result< impl Iterator< Item = T > + FusedIterator, nix::Error > where T: EventCode {
+    pub fn event_bits_of_kind< T >( &self ) -> Result< impl FusedIterator< Item = T >, Error > where T: EventCode {
         let mut buffer = Vec::new();
         self.append_event_bits_into_buffer( T::EVENT_KIND, &mut buffer )?;
         let iter = EventBitsIter::< T >::new( buffer.into() );
         Ok( iter )
     }
 
-    pub fn absolute_axis_event_bits( &self ) -> Result< impl Iterator< Item = AbsoluteAxisBit > + FusedIterator, nix::Error > {
+    pub fn absolute_axis_event_bits( &self ) -> Result< impl FusedIterator< Item = AbsoluteAxisBit >, Error > {
         let mut buffer = Vec::new();
         for axis in self.event_bits_of_kind::< AbsoluteAxis >()? {
             let info = self.get_raw_abs_info( axis )?;
@@ -518,52 +651,164 @@ impl Device {
         Ok( buffer.into_iter() )
     }
 
-    pub fn event_bits( &self ) -> Result< impl Iterator< Item = EventBit > + FusedIterator, nix::Error > {
+    /// Returns every capability of the device, including the parameters of its absolute axes.
+    pub fn event_bits( &self ) -> Result< impl FusedIterator< Item = EventBit >, Error > {
         let mut output = Vec::new();
-        let mut buffer = Vec::new();
+        output.extend( self.event_bits_of_kind::< Key >()?.map( EventBit::Key ) );
+        output.extend( self.event_bits_of_kind::< RelativeAxis >()?.map( EventBit::RelativeAxis ) );
+        output.extend( self.absolute_axis_event_bits()?.map( EventBit::AbsoluteAxis ) );
+        output.extend( self.event_bits_of_kind::< Misc >()?.map( EventBit::Misc ) );
+        output.extend( self.event_bits_of_kind::< Switch >()?.map( EventBit::Switch ) );
+        output.extend( self.event_bits_of_kind::< Led >()?.map( EventBit::Led ) );
+        output.extend( self.event_bits_of_kind::< Sound >()?.map( EventBit::Sound ) );
+        output.extend( self.event_bits_of_kind::< ForceFeedback >()?.map( EventBit::ForceFeedback ) );
 
-        buffer.clear();
-        self.append_event_bits_into_buffer( EventKind::Key, &mut buffer )?;
-        output.extend( EventBitsIter::< Key >::new( (&buffer).into() ).map( EventBit::Key ) );
+        Ok( output.into_iter() )
+    }
 
-        buffer.clear();
-        self.append_event_bits_into_buffer( EventKind::RelativeAxis, &mut buffer )?;
-        output.extend( EventBitsIter::< RelativeAxis >::new( (&buffer).into() ).map( EventBit::RelativeAxis ) );
+    /// Returns the codes of a given kind which are currently allowed to be received through this handle.
+    ///
+    /// If no mask was set for this kind then every possible code is returned.
+    pub fn event_mask< T >( &self ) -> Result< impl FusedIterator< Item = T >, Error > where T: EventCode {
+        let mut buffer = vec![ 0; 1024 ];
+        let mut mask = RawInputMask {
+            kind: T::EVENT_KIND.raw() as u32,
+            codes_size: buffer.len() as u32,
+            codes_ptr: buffer.as_mut_ptr() as u64
+        };
 
-        output.extend( self.absolute_axis_event_bits()?.map( EventBit::AbsoluteAxis ) );
+        self.check( "get the event mask", unsafe {
+            input_sys::evdev_get_mask( self.fp.as_raw_fd(), &mut mask )
+        })?;
 
-        Ok( output.into_iter() )
+        Ok( EventBitsIter::< T >::new( buffer.into() ) )
     }
 
-    fn set_clock_source( &self, clock_source: libc::c_int ) -> Result< (), nix::Error > {
-        unsafe {
-            input_sys::evdev_set_clock_id( self.fp.as_raw_fd(), &clock_source )?;
+    /// Sets which codes of a given kind will be received through this handle.
+    ///
+    /// Events which are masked out are dropped by the kernel and never
+    /// reach our buffer. The mask only applies to this particular handle;
+    /// other readers of the device are unaffected.
+    ///
+    /// To mask out whole event kinds pass `EventKind`s here; note that
+    /// `EventKind::Synchronization` has to be included in such a mask
+    /// or no `InputEventBody::Flush` events will be received.
+    pub fn set_event_mask< T, I >( &self, codes: I ) -> Result< (), Error > where T: EventCode + Into< u16 >, I: IntoIterator< Item = T > {
+        let mut buffer: Vec< u8 > = Vec::new();
+        for code in codes {
+            let code: u16 = code.into();
+            let index = code as usize / 8;
+            if index >= buffer.len() {
+                buffer.resize( index + 1, 0 );
+            }
+
+            buffer[ index ] |= 1 << (code % 8);
+        }
+
+        // An empty mask is perfectly valid, but we still need to pass a valid pointer.
+        if buffer.is_empty() {
+            buffer.push( 0 );
         }
 
+        let mask = RawInputMask {
+            kind: T::EVENT_KIND.raw() as u32,
+            codes_size: buffer.len() as u32,
+            codes_ptr: buffer.as_ptr() as u64
+        };
+
+        self.check( "set the event mask", unsafe {
+            input_sys::evdev_set_mask( self.fp.as_raw_fd(), &mask )
+        })?;
+
         Ok(())
     }
 
-    pub fn upload_force_feedback_effect( &self, effect: impl Into< RawForceFeedbackEffect > ) -> Result< ForceFeedbackEffectId, nix::Error > {
-        let mut effect = effect.into();
-        effect.id = -1; // The kernel will automatically assign an ID.
+    fn set_clock_source( &self, clock_source: libc::c_int ) -> Result< (), Error > {
+        self.check( "set the clock source", unsafe {
+            input_sys::evdev_set_clock_id( self.fp.as_raw_fd(), &clock_source )
+        })?;
 
-        let id = unsafe {
-            input_sys::evdev_start_force_feedback( self.fp.as_raw_fd(), &effect )?
-        };
+        Ok(())
+    }
 
-        assert!( id >= 0 && id <= std::i16::MAX as _ );
-        Ok( ForceFeedbackEffectId( id as i16 ) )
+    pub fn upload_force_feedback_effect( &self, effect: &ForceFeedbackEffect ) -> Result< ForceFeedbackEffectId, Error > {
+        // The kernel will automatically assign an ID.
+        self.upload_force_feedback_effect_with_id( effect, -1 )
     }
 
-    pub fn erase_force_feedback_effect( &self, id: ForceFeedbackEffectId ) -> Result< (), nix::Error > {
-        unsafe {
-            input_sys::evdev_stop_force_feedback( self.fp.as_raw_fd(), id.0 as _ )?;
-        }
+    /// Uploads an effect and returns a handle which will erase it when dropped.
+    pub fn upload_effect( &self, effect: ForceFeedbackEffect ) -> Result< Effect< '_ >, Error > {
+        let id = self.upload_force_feedback_effect( &effect )?;
+        Ok( Effect::new( self, id, effect ) )
+    }
+
+    pub(crate) fn upload_force_feedback_effect_with_id( &self, effect: &ForceFeedbackEffect, id: i16 ) -> Result< ForceFeedbackEffectId, Error > {
+        effect.with_raw( |effect| {
+            let mut effect = *effect;
+            effect.id = id;
+
+            self.check( "upload a force feedback effect", unsafe {
+                input_sys::evdev_start_force_feedback( self.fp.as_raw_fd(), &mut effect )
+            })?;
+
+            if effect.id < 0 {
+                return Err( Error::from_nix( "upload a force feedback effect", nix::Error::UnsupportedOperation ) );
+            }
+
+            Ok( ForceFeedbackEffectId( effect.id ) )
+        })
+    }
+
+    /// Returns the maximum number of force feedback effects which can be played at the same time.
+    pub fn max_simultaneous_effects( &self ) -> Result< u32, Error > {
+        let mut count: libc::c_int = 0;
+        self.check( "get the maximum number of simultaneous effects", unsafe {
+            input_sys::evdev_get_maximum_simultaneous_force_feedback_effect_count( self.fp.as_raw_fd(), &mut count )
+        })?;
+
+        Ok( count.max( 0 ) as u32 )
+    }
+
+    /// Returns the force feedback effects and features supported by the device.
+    pub fn force_feedback_capabilities( &self ) -> Result< ForceFeedbackCapabilities, Error > {
+        let bits = self.event_bits_of_kind::< ForceFeedback >()?;
+        let max_simultaneous_effects = self.max_simultaneous_effects()?;
+        Ok( ForceFeedbackCapabilities::from_bits( bits, max_simultaneous_effects ) )
+    }
+
+    /// Sets the global strength of every force feedback effect.
+    ///
+    /// The `gain` ranges from `0.0` to `1.0`. Only works if the device
+    /// supports `ForceFeedback::Gain`.
+    pub fn set_force_feedback_gain( &self, gain: f32 ) -> Result< (), Error > {
+        self.emit( InputEventBody::Other {
+            kind: EventKind::ForceFeedback,
+            code: input_sys::FF_GAIN,
+            value: fraction_to_raw( gain )
+        })
+    }
+
+    /// Sets the strength with which the device is pulled towards its center.
+    ///
+    /// The `strength` ranges from `0.0` (disabled) to `1.0`. Only works if the
+    /// device supports `ForceFeedback::Autocenter`.
+    pub fn set_autocenter( &self, strength: f32 ) -> Result< (), Error > {
+        self.emit( InputEventBody::Other {
+            kind: EventKind::ForceFeedback,
+            code: input_sys::FF_AUTOCENTER,
+            value: fraction_to_raw( strength )
+        })
+    }
+
+    pub fn erase_force_feedback_effect( &self, id: ForceFeedbackEffectId ) -> Result< (), Error > {
+        self.check( "erase a force feedback effect", unsafe {
+            input_sys::evdev_stop_force_feedback( self.fp.as_raw_fd(), id.0 as _ )
+        })?;
 
         Ok(())
     }
 
-    pub fn enable_force_feedback_effect( &self, effect_id: ForceFeedbackEffectId, cycle_count: i32 ) -> Result< (), io::Error > {
+    pub fn enable_force_feedback_effect( &self, effect_id: ForceFeedbackEffectId, cycle_count: i32 ) -> Result< (), Error > {
         self.emit( InputEventBody::Other {
             kind: EventKind::ForceFeedback,
             code: effect_id.0 as u16,
@@ -571,7 +816,7 @@ impl Device {
         })
     }
 
-    pub fn disable_force_feedback_effect( &self, effect_id: ForceFeedbackEffectId ) -> Result< (), io::Error > {
+    pub fn disable_force_feedback_effect( &self, effect_id: ForceFeedbackEffectId ) -> Result< (), Error > {
         self.emit( InputEventBody::Other {
             kind: EventKind::ForceFeedback,
             code: effect_id.0 as u16,
@@ -581,28 +826,105 @@ impl Device {
 
     /// Grabs the device for exclusive access.
     ///
-    /// No one else will receive any events from it.
-    pub fn grab( &self ) -> Result< (), nix::Error > {
-        unsafe {
-            input_sys::evdev_grab_or_release( self.fp.as_raw_fd(), 1 )?;
-        }
+    /// No one else will receive any events from it. Fails with `ErrorKind::Busy`
+    /// if the device is already grabbed by someone else.
+    pub fn grab( &self ) -> Result< (), Error > {
+        self.check( "grab the device", unsafe {
+            input_sys::evdev_grab_or_release( self.fp.as_raw_fd(), 1 )
+        })?;
 
         Ok(())
     }
 
     /// Releases the device from exclusive access.
-    pub fn release( &self ) -> Result< (), nix::Error > {
-        unsafe {
-            input_sys::evdev_grab_or_release( self.fp.as_raw_fd(), 0 )?;
-        }
+    pub fn release( &self ) -> Result< (), Error > {
+        self.check( "release the device", unsafe {
+            input_sys::evdev_grab_or_release( self.fp.as_raw_fd(), 0 )
+        })?;
 
         Ok(())
     }
 
+    /// Revokes access to the device through this file descriptor.
+    ///
+    /// Every subsequent operation will fail with an `ErrorKind::Revoked` error.
+    /// This also affects every other process which received a copy of this
+    /// file descriptor, which makes it possible to safely hand out devices
+    /// and take them back later; there it shows up as `ErrorKind::DeviceGone`.
+    pub fn revoke( &self ) -> Result< (), Error > {
+        self.check( "revoke access to the device", unsafe {
+            input_sys::evdev_revoke( self.fp.as_raw_fd(), 0 )
+        })?;
+
+        self.is_revoked.store( true, Ordering::SeqCst );
+        Ok(())
+    }
+
+    /// Checks whether access to the device was revoked through this handle.
+    ///
+    /// The kernel reports both a revocation and an unplugged device with `ENODEV`,
+    /// so if the access was revoked elsewhere, e.g. by the process from which
+    /// the file descriptor was received, this can't be reliably told apart
+    /// from the device being gone, and operations fail with `ErrorKind::DeviceGone`.
+    pub fn is_revoked( &self ) -> bool {
+        self.is_revoked.load( Ordering::SeqCst )
+    }
+
+    fn check_revoked( &self, error: Error ) -> Error {
+        if error.raw_os_error() == Some( libc::ENODEV ) && self.is_revoked() {
+            error.into_revoked()
+        } else {
+            error
+        }
+    }
+
+    fn check< T >( &self, operation: &'static str, result: Result< T, nix::Error > ) -> Result< T, Error > {
+        result.context( operation ).map_err( |error| self.check_revoked( error ) )
+    }
+
     /// Emits a given event just as if it was sent by the device itself.
     ///
     /// Makes sense only when the device is *not* grabbed for exclusive access.
-    pub fn emit< T >( &self, body: T ) -> Result< (), io::Error > where T: AsRef< InputEventBody > {
-        emit_into( &self.fp, body )
+    ///
+    /// Use a `KeyGuard` to make sure that injected key presses are released.
+    pub fn emit< T >( &self, body: T ) -> Result< (), Error > where T: AsRef< InputEventBody > {
+        emit_into( &self.fp, body ).map_err( |error| self.check_revoked( error ) )
     }
 }
+
+#[test]
+fn test_effect_status_round_trip() {
+    let raw_event = RawInputEvent {
+        timestamp: Timestamp::default(),
+        kind: EventKind::ForceFeedbackStatus.raw(),
+        code: 3,
+        value: input_sys::FF_STATUS_PLAYING
+    };
+
+    let event: InputEvent = raw_event.clone().into();
+    assert_eq!( event.body, InputEventBody::EffectStatus { effect_id: 3, playing: true } );
+    assert_eq!( RawInputEvent::from( event ), raw_event );
+}
+
+#[cfg(test)]
+pub(crate) fn test_device() -> Device {
+    Device {
+        fp: File::open( "/dev/null" ).unwrap(),
+        is_revoked: AtomicBool::new( false )
+    }
+}
+
+#[test]
+fn test_enodev_is_reported_as_revoked_only_after_revoke() {
+    let enodev = || Error::from_nix( "read an event", nix::Error::Sys( nix::errno::Errno::ENODEV ) );
+
+    let device = test_device();
+    assert_eq!( device.check_revoked( enodev() ).kind(), crate::error::ErrorKind::DeviceGone );
+    assert!( !device.is_revoked() );
+
+    device.is_revoked.store( true, Ordering::SeqCst );
+    assert_eq!( device.check_revoked( enodev() ).kind(), crate::error::ErrorKind::Revoked );
+
+    let error = device.check_revoked( Error::from_nix( "read an event", nix::Error::Sys( nix::errno::Errno::EIO ) ) );
+    assert_eq!( error.kind(), crate::error::ErrorKind::Other );
+}
@@ -50,7 +50,7 @@ pub struct RawForceFeedbackEnvelope {
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(C)]
 pub struct RawForceFeedbackConstantEffect {
-    pub level: u16,
+    pub level: i16,
     pub envelope: RawForceFeedbackEnvelope
 }
 
@@ -58,7 +58,7 @@ pub struct RawForceFeedbackConstantEffect {
 #[repr(C)]
 pub struct RawForceFeedbackRampEffect {
     pub start_level: i16,
-    pub end_level: u16,
+    pub end_level: i16,
     pub envelope: RawForceFeedbackEnvelope
 }
 
@@ -116,23 +116,21 @@ pub struct RawForceFeedbackEffect {
 }
 
 pub const FF_RUMBLE: u16 = 0x50;
-#[allow(dead_code)]
 pub const FF_PERIODIC: u16 = 0x51;
-#[allow(dead_code)]
 pub const FF_CONSTANT: u16 = 0x52;
-#[allow(dead_code)]
 pub const FF_SPRING: u16 = 0x53;
-#[allow(dead_code)]
 pub const FF_FRICTION: u16 = 0x54;
-#[allow(dead_code)]
 pub const FF_DAMPER: u16 = 0x55;
-#[allow(dead_code)]
 pub const FF_INERTIA: u16 = 0x56;
-#[allow(dead_code)]
 pub const FF_RAMP: u16 = 0x57;
 
+pub const FF_WAVEFORM_MIN: u16 = 0x58;
+pub const FF_WAVEFORM_MAX: u16 = 0x5d;
+
+pub const FF_STATUS_STOPPED: i32 = 0x00;
+pub const FF_STATUS_PLAYING: i32 = 0x01;
+
 pub const FF_GAIN: u16 = 0x60;
-#[allow(dead_code)]
 pub const FF_AUTOCENTER: u16 = 0x61;
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
@@ -165,7 +163,7 @@ impl Timestamp {
     }
 
     pub fn as_f64( self ) -> f64 {
-        self.sec as f64 + self.usec as f64 / 1000_000.0
+        self.sec as f64 + self.usec as f64 / 1_000_000.0
     }
 }
 
@@ -185,6 +183,13 @@ pub struct RawInputEvent {
     pub value: i32
 }
 
+#[repr(C)]
+pub struct RawInputMask {
+    pub kind: u32,
+    pub codes_size: u32,
+    pub codes_ptr: u64
+}
+
 define_enum! {
     #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
     enum EventKind {
@@ -291,6 +296,8 @@ define_enum! {
         Keypad3 = 81,
         Keypad0 = 82,
         KeypadDot = 83,
+        // The extra key next to the left shift on ISO keyboards.
+        Key102nd = 86,
         F11 = 87,
         F12 = 88,
         KeypadEnter = 96,
@@ -342,6 +349,17 @@ define_enum! {
         PadLeft = 0x222,
         PadRight = 0x223,
 
+        ToolPen = 0x140,
+        ToolRubber = 0x141,
+        ToolFinger = 0x145,
+        ToolQuintTap = 0x148,
+        Touch = 0x14a,
+        Stylus = 0x14b,
+        Stylus2 = 0x14c,
+        ToolDoubleTap = 0x14d,
+        ToolTripleTap = 0x14e,
+        ToolQuadTap = 0x14f,
+
         ButtonMisc = 0x100,
         TriggerHappy = 0x2c0
     }
@@ -357,8 +375,10 @@ define_enum! {
         RX = 3,
         RY = 4,
         RZ = 5,
+        HorizontalWheel = 6,
         Wheel = 8,
-        WheelHiRes = 11
+        WheelHiRes = 11,
+        HorizontalWheelHiRes = 12
     }
 }
 
@@ -378,7 +398,28 @@ define_enum! {
         Hat1Y = 19,
         Hat2X = 20,
         Hat2Y = 21,
-        Misc = 40
+        Pressure = 24,
+        Distance = 25,
+        TiltX = 26,
+        TiltY = 27,
+        Misc = 40,
+
+        // https://www.kernel.org/doc/html/latest/input/multi-touch-protocol.html
+        MtSlot = 0x2f,
+        MtTouchMajor = 0x30,
+        MtTouchMinor = 0x31,
+        MtWidthMajor = 0x32,
+        MtWidthMinor = 0x33,
+        MtOrientation = 0x34,
+        MtPositionX = 0x35,
+        MtPositionY = 0x36,
+        MtToolType = 0x37,
+        MtBlobId = 0x38,
+        MtTrackingId = 0x39,
+        MtPressure = 0x3a,
+        MtDistance = 0x3b,
+        MtToolX = 0x3c,
+        MtToolY = 0x3d
     }
 }
 
@@ -401,15 +442,127 @@ define_enum! {
     #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
     enum ForceFeedback {
         Other( u16 ),
-        Rumble = 0x50
+        Rumble = 0x50,
+        Periodic = 0x51,
+        Constant = 0x52,
+        Spring = 0x53,
+        Friction = 0x54,
+        Damper = 0x55,
+        Inertia = 0x56,
+        Ramp = 0x57,
+        Square = 0x58,
+        Triangle = 0x59,
+        Sine = 0x5a,
+        SawUp = 0x5b,
+        SawDown = 0x5c,
+        Custom = 0x5d,
+        Gain = 0x60,
+        Autocenter = 0x61
+    }
+}
+
+define_enum! {
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum Misc {
+        Other( u16 ),
+        Serial = 0x00,
+        PulseLed = 0x01,
+        Gesture = 0x02,
+        Raw = 0x03,
+        Scan = 0x04,
+        Timestamp = 0x05
+    }
+}
+
+define_enum! {
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum Switch {
+        Other( u16 ),
+        Lid = 0x00,
+        TabletMode = 0x01,
+        HeadphoneInsert = 0x02,
+        RfKillAll = 0x03,
+        MicrophoneInsert = 0x04,
+        Dock = 0x05,
+        LineoutInsert = 0x06,
+        JackPhysicalInsert = 0x07,
+        VideoOutInsert = 0x08,
+        CameraLensCover = 0x09,
+        KeypadSlide = 0x0a,
+        FrontProximity = 0x0b,
+        RotateLock = 0x0c,
+        LineinInsert = 0x0d,
+        MuteDevice = 0x0e,
+        PenInserted = 0x0f,
+        MachineCover = 0x10
+    }
+}
+
+define_enum! {
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum Led {
+        Other( u16 ),
+        NumLock = 0x00,
+        CapsLock = 0x01,
+        ScrollLock = 0x02,
+        Compose = 0x03,
+        Kana = 0x04,
+        Sleep = 0x05,
+        Suspend = 0x06,
+        Mute = 0x07,
+        Misc = 0x08,
+        Mail = 0x09,
+        Charging = 0x0a
+    }
+}
+
+define_enum! {
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum Sound {
+        Other( u16 ),
+        Click = 0x00,
+        Bell = 0x01,
+        Tone = 0x02
+    }
+}
+
+define_enum! {
+    // Source: linux/input-event-codes.h
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum InputProperty {
+        Other( u16 ),
+        Pointer = 0x00,
+        Direct = 0x01,
+        ButtonPad = 0x02,
+        SemiMultitouch = 0x03,
+        TopButtonPad = 0x04,
+        PointingStick = 0x05,
+        Accelerometer = 0x06
+    }
+}
+
+define_enum! {
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum ForceFeedbackWaveform {
+        Other( u16 ),
+        Square = 0x58,
+        Triangle = 0x59,
+        Sine = 0x5a,
+        SawUp = 0x5b,
+        SawDown = 0x5c,
+        Custom = 0x5d
     }
 }
 
 ioctl_write_int!( evdev_grab_or_release, b'E', 0x90 );
+ioctl_write_int!( evdev_revoke, b'E', 0x91 );
+ioctl_read!( evdev_get_version, b'E', 0x01, libc::c_int );
 ioctl_read!( evdev_get_id, b'E', 0x02, RawDeviceId );
+ioctl_read!( evdev_get_autorepeat, b'E', 0x03, [libc::c_uint; 2] );
 ioctl_write_ptr!( evdev_set_clock_id, b'E', 0xa0, libc::c_int );
+ioctl_read!( evdev_get_mask, b'E', 0x92, RawInputMask );
+ioctl_write_ptr!( evdev_set_mask, b'E', 0x93, RawInputMask );
 
-ioctl_write_ptr!( evdev_start_force_feedback, b'E', 0x80, RawForceFeedbackEffect );
 ioctl_write_int!( evdev_stop_force_feedback, b'E', 0x81 );
 ioctl_read!( evdev_get_maximum_simultaneous_force_feedback_effect_count, b'E', 0x84, libc::c_int );
 
@@ -418,6 +571,22 @@ pub unsafe fn evdev_get_event_bits( fd: libc::c_int, kind: EventKind, data: *mut
     nix::errno::Errno::result( result )
 }
 
+pub unsafe fn evdev_get_key_state( fd: libc::c_int, data: *mut u8, length: usize ) -> nix::Result< libc::c_int > {
+    let result = libc::ioctl( fd, request_code_read!( b'E', 0x18, length ), data );
+    nix::errno::Errno::result( result )
+}
+
+pub unsafe fn evdev_get_properties( fd: libc::c_int, data: *mut u8, length: usize ) -> nix::Result< libc::c_int > {
+    let result = libc::ioctl( fd, request_code_read!( b'E', 0x09, length ), data );
+    nix::errno::Errno::result( result )
+}
+
+// The kernel writes the ID of the effect back into the struct, so this needs a mutable pointer.
+pub unsafe fn evdev_start_force_feedback( fd: libc::c_int, effect: *mut RawForceFeedbackEffect ) -> nix::Result< libc::c_int > {
+    let result = libc::ioctl( fd, request_code_write!( b'E', 0x80, std::mem::size_of::< RawForceFeedbackEffect >() ), effect );
+    nix::errno::Errno::result( result )
+}
+
 pub unsafe fn evdev_get_abs_info( fd: libc::c_int, axis: AbsoluteAxis ) -> nix::Result< RawAbsInfo > {
     let mut abs_info = std::mem::MaybeUninit::uninit();
     let result = libc::ioctl( fd, request_code_read!( b'E', 0x40 + axis.raw() as usize, std::mem::size_of::< RawAbsInfo >() ), abs_info.as_mut_ptr() );
@@ -427,3 +596,8 @@ pub unsafe fn evdev_get_abs_info( fd: libc::c_int, axis: AbsoluteAxis ) -> nix::
 
     Ok( abs_info.assume_init() )
 }
+
+pub unsafe fn evdev_set_abs_info( fd: libc::c_int, axis: AbsoluteAxis, abs_info: &RawAbsInfo ) -> nix::Result< () > {
+    let result = libc::ioctl( fd, request_code_write!( b'E', 0xc0 + axis.raw() as usize, std::mem::size_of::< RawAbsInfo >() ), abs_info as *const RawAbsInfo );
+    nix::errno::Errno::result( result ).map( |_| () )
+}
@@ -4,21 +4,64 @@ extern crate nix;
 #[macro_use]
 mod macros;
 
+mod calibration;
+mod error;
 mod event_bits_iter;
+mod force_feedback;
+mod force_feedback_forwarder;
+mod force_feedback_simulator;
+mod grab_guard;
 mod input;
 mod input_sys;
+mod rumble_pattern;
+mod scroll_wheel;
+mod stuck_keys;
 mod uinput;
 mod uinput_sys;
 mod utils;
+mod virtual_absolute_pointer;
+mod virtual_gamepad;
+mod virtual_keyboard;
+mod virtual_mouse;
+mod virtual_tablet;
+mod virtual_touch_device;
 
 pub use crate::{
+    calibration::{
+        AbsoluteAxisCalibration
+    },
+    error::{
+        Error,
+        ErrorKind
+    },
+    force_feedback::{
+        Effect,
+        ForceFeedbackCapabilities,
+        ForceFeedbackCondition,
+        ForceFeedbackDuration,
+        ForceFeedbackEffect,
+        ForceFeedbackEffectId,
+        ForceFeedbackEffectKind,
+        ForceFeedbackEnvelope,
+        ForceFeedbackTrigger
+    },
+    force_feedback_forwarder::{
+        ForceFeedbackForwarder
+    },
+    force_feedback_simulator::{
+        ForceFeedbackOutput,
+        ForceFeedbackSimulator
+    },
+    grab_guard::{
+        GrabGuard
+    },
     input::{
+        AbsInfo,
         AbsoluteAxisBit,
         DeviceId,
         Device,
         EventBit,
-        ForceFeedbackDuration,
-        ForceFeedbackEffectKind,
+        EventCode,
         InputEvent,
         InputEventBody,
         poll_read
@@ -28,16 +71,64 @@ pub use crate::{
         Bus,
         EventKind,
         ForceFeedback,
+        ForceFeedbackWaveform,
+        InputProperty,
         Key,
+        Led,
+        Misc,
         RawInputEvent,
         RelativeAxis,
+        Sound,
+        Switch,
         Timestamp
     },
+    rumble_pattern::{
+        RumblePattern,
+        RumblePatternParseError,
+        RumblePlayer,
+        RumbleStep
+    },
+    stuck_keys::{
+        KeyGuard
+    },
     uinput::{
         DeviceCreateError,
         ForceFeedbackEffectErase,
         ForceFeedbackEffectUpload,
         ForceFeedbackRequest,
-        VirtualDevice
+        FrameBuilder,
+        VirtualDevice,
+        VirtualDeviceBuilder,
+        VirtualDevicePaths
+    },
+    scroll_wheel::{
+        WHEEL_UNITS_PER_NOTCH
+    },
+    virtual_absolute_pointer::{
+        VirtualAbsolutePointer
+    },
+    virtual_gamepad::{
+        GamepadPreset,
+        GamepadState,
+        VirtualGamepad
+    },
+    virtual_keyboard::{
+        KeyboardLayout,
+        Keystroke,
+        VirtualKeyboard
+    },
+    virtual_mouse::{
+        MousePath,
+        VirtualMouse
+    },
+    virtual_tablet::{
+        PenSample,
+        PenTool,
+        VirtualTablet,
+        pressure_curve
+    },
+    virtual_touch_device::{
+        TouchDeviceKind,
+        VirtualTouchDevice
     }
 };
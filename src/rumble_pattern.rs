@@ -0,0 +1,318 @@
+use {
+    std::{
+        fmt,
+        str::{
+            FromStr
+        },
+        sync::{
+            Arc,
+            mpsc
+        },
+        thread,
+        time::{
+            Duration,
+            Instant
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        force_feedback::{
+            ForceFeedbackDuration,
+            ForceFeedbackEffect,
+            ForceFeedbackEffectKind,
+            ForceFeedbackTrigger
+        },
+        input::{
+            Device
+        }
+    }
+};
+
+/// A single step of a `RumblePattern`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RumbleStep {
+    pub strong_magnitude: u16,
+    pub weak_magnitude: u16,
+    pub duration: Duration
+}
+
+/// A sequence of rumble steps, e.g. for a short-long-short notification.
+///
+/// Patterns can be parsed from a compact string like `200:ff00/100:0/200:ff00`,
+/// where every step is separated by a `/` and consists of the step's duration
+/// in milliseconds and its magnitudes as a hexadecimal number, with the upper byte
+/// being the strong motor's magnitude and the lower byte the weak motor's.
+/// The pattern can be suffixed with `*N` to play it `N` times, or with just
+/// a `*` to play it forever.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RumblePattern {
+    pub steps: Vec< RumbleStep >,
+    /// How many times the whole pattern is played; zero means forever.
+    pub repeat_count: u32
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RumblePatternParseError {
+    Empty,
+    InvalidStep( String ),
+    InvalidRepeatCount( String )
+}
+
+impl fmt::Display for RumblePatternParseError {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        match *self {
+            RumblePatternParseError::Empty => write!( fmt, "the rumble pattern is empty" ),
+            RumblePatternParseError::InvalidStep( ref step ) => write!( fmt, "invalid rumble pattern step: '{}'", step ),
+            RumblePatternParseError::InvalidRepeatCount( ref count ) => write!( fmt, "invalid rumble pattern repeat count: '{}'", count )
+        }
+    }
+}
+
+impl std::error::Error for RumblePatternParseError {}
+
+impl FromStr for RumblePattern {
+    type Err = RumblePatternParseError;
+    fn from_str( string: &str ) -> Result< Self, Self::Err > {
+        let string = string.trim();
+        let (steps, repeat_count) = match string.find( '*' ) {
+            Some( index ) => {
+                let count = &string[ index + 1.. ];
+                let count = if count.is_empty() {
+                    0
+                } else {
+                    match count.parse() {
+                        Ok( count ) if count > 0 => count,
+                        _ => return Err( RumblePatternParseError::InvalidRepeatCount( count.to_owned() ) )
+                    }
+                };
+
+                (&string[ ..index ], count)
+            },
+            None => (string, 1)
+        };
+
+        if steps.is_empty() {
+            return Err( RumblePatternParseError::Empty );
+        }
+
+        let steps = steps.split( '/' ).map( |step| {
+            let invalid = || RumblePatternParseError::InvalidStep( step.to_owned() );
+            let mut parts = step.splitn( 2, ':' );
+            let duration = parts.next().ok_or_else( invalid )?.trim().parse::< u64 >().map_err( |_| invalid() )?;
+            let magnitude = u16::from_str_radix( parts.next().ok_or_else( invalid )?.trim(), 16 ).map_err( |_| invalid() )?;
+
+            Ok( RumbleStep {
+                strong_magnitude: (magnitude >> 8) * 0x101,
+                weak_magnitude: (magnitude & 0xff) * 0x101,
+                duration: Duration::from_millis( duration )
+            })
+        }).collect::< Result< Vec< _ >, _ > >()?;
+
+        Ok( RumblePattern {
+            steps,
+            repeat_count
+        })
+    }
+}
+
+impl fmt::Display for RumblePattern {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            if index != 0 {
+                write!( fmt, "/" )?;
+            }
+
+            let magnitude = ((step.strong_magnitude >> 8) << 8) | (step.weak_magnitude >> 8);
+            write!( fmt, "{}:{:x}", step.duration.as_millis(), magnitude )?;
+        }
+
+        match self.repeat_count {
+            0 => write!( fmt, "*" ),
+            1 => Ok(()),
+            count => write!( fmt, "*{}", count )
+        }
+    }
+}
+
+impl RumblePattern {
+    /// The duration of a single repetition of the pattern.
+    pub fn period( &self ) -> Duration {
+        self.steps.iter().map( |step| step.duration ).sum()
+    }
+
+    /// Returns the step which should be playing after a given time since the pattern started,
+    /// or `None` if the pattern has already finished.
+    ///
+    /// This can be used to drive the pattern from an existing event loop.
+    pub fn step_at( &self, elapsed: Duration ) -> Option< &RumbleStep > {
+        let period = self.period().as_micros();
+        if period == 0 {
+            return None;
+        }
+
+        let elapsed = elapsed.as_micros();
+        if self.repeat_count != 0 && elapsed / period >= self.repeat_count as u128 {
+            return None;
+        }
+
+        let mut offset = elapsed % period;
+        for step in &self.steps {
+            let duration = step.duration.as_micros();
+            if offset < duration {
+                return Some( step );
+            }
+
+            offset -= duration;
+        }
+
+        None
+    }
+
+    /// Plays the pattern on a background thread.
+    ///
+    /// The pattern is played through a single effect which is uploaded into
+    /// the device and updated in-place at the start of every step.
+    pub fn play( &self, device: Arc< Device > ) -> RumblePlayer {
+        let (sender, receiver) = mpsc::channel();
+        let pattern = self.clone();
+        let thread = thread::spawn( move || pattern.run( &device, receiver ) );
+
+        RumblePlayer {
+            sender,
+            thread: Some( thread )
+        }
+    }
+
+    fn run( &self, device: &Device, cancel: mpsc::Receiver< () > ) -> Result< (), Error > {
+        if self.period() == Duration::from_millis( 0 ) {
+            return Ok(());
+        }
+
+        let mut effect = device.upload_effect( ForceFeedbackEffect {
+            id: -1,
+            direction: 0,
+            kind: ForceFeedbackEffectKind::Rumble {
+                strong_magnitude: 0,
+                weak_magnitude: 0
+            },
+            duration: ForceFeedbackDuration::Infinite,
+            delay: Duration::from_millis( 0 ),
+            trigger: ForceFeedbackTrigger::default()
+        })?;
+
+        effect.play( 1 )?;
+
+        // We keep track of absolute deadlines so that the timing doesn't drift.
+        let mut deadline = Instant::now();
+        let mut repetition = 0;
+        while self.repeat_count == 0 || repetition < self.repeat_count {
+            for step in &self.steps {
+                effect.update( ForceFeedbackEffectKind::Rumble {
+                    strong_magnitude: step.strong_magnitude,
+                    weak_magnitude: step.weak_magnitude
+                })?;
+
+                deadline += step.duration;
+                let timeout = deadline.saturating_duration_since( Instant::now() );
+                match cancel.recv_timeout( timeout ) {
+                    Err( mpsc::RecvTimeoutError::Timeout ) => {},
+                    Ok(()) | Err( mpsc::RecvTimeoutError::Disconnected ) => return effect.stop()
+                }
+            }
+
+            repetition += 1;
+        }
+
+        effect.stop()
+    }
+}
+
+/// A handle to a `RumblePattern` which is playing on a background thread.
+///
+/// The pattern is canceled when this is dropped.
+pub struct RumblePlayer {
+    sender: mpsc::Sender< () >,
+    thread: Option< thread::JoinHandle< Result< (), Error > > >
+}
+
+impl RumblePlayer {
+    /// Stops the pattern and waits until the rumble is stopped.
+    pub fn cancel( mut self ) -> Result< (), Error > {
+        let _ = self.sender.send( () );
+        self.join()
+    }
+
+    /// Waits until the pattern finishes playing.
+    pub fn wait( mut self ) -> Result< (), Error > {
+        self.join()
+    }
+
+    fn join( &mut self ) -> Result< (), Error > {
+        match self.thread.take() {
+            Some( thread ) => thread.join().unwrap_or_else( |_| Err( Error::other( "play the rumble pattern", "the rumble thread panicked".to_owned() ) ) ),
+            None => Ok(())
+        }
+    }
+}
+
+impl Drop for RumblePlayer {
+    fn drop( &mut self ) {
+        let _ = self.sender.send( () );
+        let _ = self.join();
+    }
+}
+
+#[test]
+fn test_rumble_pattern_parse() {
+    let pattern: RumblePattern = "200:ff00/100:0/200:ff80".parse().unwrap();
+    assert_eq!( pattern, RumblePattern {
+        steps: vec![
+            RumbleStep { strong_magnitude: 0xffff, weak_magnitude: 0, duration: Duration::from_millis( 200 ) },
+            RumbleStep { strong_magnitude: 0, weak_magnitude: 0, duration: Duration::from_millis( 100 ) },
+            RumbleStep { strong_magnitude: 0xffff, weak_magnitude: 0x8080, duration: Duration::from_millis( 200 ) }
+        ],
+        repeat_count: 1
+    });
+
+    assert_eq!( pattern.to_string(), "200:ff00/100:0/200:ff80" );
+}
+
+#[test]
+fn test_rumble_pattern_parse_repeat() {
+    let pattern: RumblePattern = "50:ffff*3".parse().unwrap();
+    assert_eq!( pattern.repeat_count, 3 );
+    assert_eq!( pattern.to_string(), "50:ffff*3" );
+
+    let pattern: RumblePattern = "50:ffff/50:0*".parse().unwrap();
+    assert_eq!( pattern.repeat_count, 0 );
+    assert_eq!( pattern.to_string(), "50:ffff/50:0*" );
+}
+
+#[test]
+fn test_rumble_pattern_parse_errors() {
+    assert_eq!( "".parse::< RumblePattern >(), Err( RumblePatternParseError::Empty ) );
+    assert_eq!( "200".parse::< RumblePattern >(), Err( RumblePatternParseError::InvalidStep( "200".to_owned() ) ) );
+    assert_eq!( "200:xyz".parse::< RumblePattern >(), Err( RumblePatternParseError::InvalidStep( "200:xyz".to_owned() ) ) );
+    assert_eq!( "200:ff*0".parse::< RumblePattern >(), Err( RumblePatternParseError::InvalidRepeatCount( "0".to_owned() ) ) );
+}
+
+#[test]
+fn test_rumble_pattern_step_at() {
+    let pattern: RumblePattern = "200:ff00/100:0*2".parse().unwrap();
+    let ms = Duration::from_millis;
+    assert_eq!( pattern.step_at( ms( 0 ) ).unwrap().strong_magnitude, 0xffff );
+    assert_eq!( pattern.step_at( ms( 199 ) ).unwrap().strong_magnitude, 0xffff );
+    assert_eq!( pattern.step_at( ms( 200 ) ).unwrap().strong_magnitude, 0 );
+    assert_eq!( pattern.step_at( ms( 300 ) ).unwrap().strong_magnitude, 0xffff );
+    assert_eq!( pattern.step_at( ms( 599 ) ).unwrap().strong_magnitude, 0 );
+    assert_eq!( pattern.step_at( ms( 600 ) ), None );
+}
+
+#[test]
+fn test_rumble_pattern_run_with_zero_period() {
+    let pattern: RumblePattern = "0:ffff*".parse().unwrap();
+    let (_sender, receiver) = mpsc::channel();
+    assert!( pattern.run( &crate::input::test_device(), receiver ).is_ok() );
+}
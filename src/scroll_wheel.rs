@@ -0,0 +1,90 @@
+use {
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            InputEventBody
+        },
+        input_sys::{
+            RelativeAxis
+        },
+        uinput::{
+            VirtualDevice
+        }
+    }
+};
+
+/// The amount of high resolution wheel units which make up a single notch.
+pub const WHEEL_UNITS_PER_NOTCH: i32 = 120;
+
+fn take_whole_notches( remainder: &mut i32, delta: i32 ) -> i32 {
+    // Summed in 64 bits, since a huge delta on top of the remainder could overflow.
+    let total = *remainder as i64 + delta as i64;
+    let units_per_notch = WHEEL_UNITS_PER_NOTCH as i64;
+    *remainder = (total % units_per_notch) as i32;
+    (total / units_per_notch) as i32
+}
+
+/// The vertical and horizontal wheels of a virtual device, scrolled in high resolution units.
+///
+/// Alongside the high resolution axes the plain wheel axes are moved
+/// whenever the scrolled amount adds up to whole notches.
+#[derive(Default)]
+pub(crate) struct ScrollWheels {
+    remainder: i32,
+    horizontal_remainder: i32
+}
+
+impl ScrollWheels {
+    pub const AXES: [RelativeAxis; 4] = [
+        RelativeAxis::Wheel,
+        RelativeAxis::WheelHiRes,
+        RelativeAxis::HorizontalWheel,
+        RelativeAxis::HorizontalWheelHiRes
+    ];
+
+    pub fn scroll( &mut self, device: &VirtualDevice, amount: i32 ) -> Result< (), Error > {
+        let notches = take_whole_notches( &mut self.remainder, amount );
+        emit_scroll( device, RelativeAxis::WheelHiRes, RelativeAxis::Wheel, amount, notches )
+    }
+
+    pub fn scroll_horizontally( &mut self, device: &VirtualDevice, amount: i32 ) -> Result< (), Error > {
+        let notches = take_whole_notches( &mut self.horizontal_remainder, amount );
+        emit_scroll( device, RelativeAxis::HorizontalWheelHiRes, RelativeAxis::HorizontalWheel, amount, notches )
+    }
+}
+
+fn emit_scroll( device: &VirtualDevice, hi_res_axis: RelativeAxis, axis: RelativeAxis, amount: i32, notches: i32 ) -> Result< (), Error > {
+    let mut frame = device.frame();
+    if amount != 0 {
+        frame.push( InputEventBody::RelativeMove { axis: hi_res_axis, delta: amount } );
+    }
+    if notches != 0 {
+        frame.push( InputEventBody::RelativeMove { axis, delta: notches } );
+    }
+
+    frame.flush()
+}
+
+#[test]
+fn test_wheel_notches() {
+    let mut remainder = 0;
+    assert_eq!( take_whole_notches( &mut remainder, 60 ), 0 );
+    assert_eq!( take_whole_notches( &mut remainder, 60 ), 1 );
+    assert_eq!( take_whole_notches( &mut remainder, 300 ), 2 );
+    assert_eq!( remainder, 60 );
+    assert_eq!( take_whole_notches( &mut remainder, -200 ), -1 );
+    assert_eq!( remainder, -20 );
+}
+
+#[test]
+fn test_wheel_notches_with_a_huge_amount() {
+    let mut remainder = 100;
+    assert_eq!( take_whole_notches( &mut remainder, i32::MAX ), ((i32::MAX as i64 + 100) / 120) as i32 );
+    assert_eq!( remainder, ((i32::MAX as i64 + 100) % 120) as i32 );
+
+    let mut remainder = -100;
+    assert_eq!( take_whole_notches( &mut remainder, i32::MIN ), ((i32::MIN as i64 - 100) / 120) as i32 );
+    assert_eq!( remainder, ((i32::MIN as i64 - 100) % 120) as i32 );
+}
@@ -0,0 +1,217 @@
+use {
+    std::{
+        collections::{
+            BTreeSet
+        },
+        fs::{
+            File
+        },
+        panic,
+        sync::{
+            Arc,
+            Mutex,
+            MutexGuard,
+            Once,
+            TryLockError
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            Device,
+            InputEventBody,
+            emit_frame_into
+        },
+        input_sys::{
+            Key
+        }
+    }
+};
+
+/// The keys and buttons which were reported as pressed and not released yet.
+#[derive(Default)]
+pub(crate) struct PressedKeys {
+    codes: Mutex< BTreeSet< u16 > >
+}
+
+impl PressedKeys {
+    fn codes( &self ) -> MutexGuard< '_, BTreeSet< u16 > > {
+        // This is also used from a panic hook, so a poisoned lock must not stop us.
+        self.codes.lock().unwrap_or_else( |error| error.into_inner() )
+    }
+
+    /// Updates the set with events which were successfully emitted.
+    pub(crate) fn track( &self, bodies: &[InputEventBody] ) {
+        let mut codes = self.codes();
+        for body in bodies {
+            match *body {
+                InputEventBody::KeyPress( key ) => { codes.insert( key.raw() ); },
+                InputEventBody::KeyRelease( key ) => { codes.remove( &key.raw() ); },
+                _ => {}
+            }
+        }
+    }
+
+    pub(crate) fn keys( &self ) -> Vec< Key > {
+        self.codes().iter().map( |&code| Key::from( code ) ).collect()
+    }
+
+    /// Returns the events which release every pressed key.
+    pub(crate) fn releases( &self ) -> Vec< InputEventBody > {
+        releases_of( &self.codes() )
+    }
+
+    /// Releases every pressed key through a given file, unless the set is locked by someone else.
+    ///
+    /// This is used from the panic hook, where waiting for a lock held
+    /// by the panicking thread would deadlock.
+    fn try_release_all_into( &self, fp: &File ) {
+        let mut codes = match self.codes.try_lock() {
+            Ok( codes ) => codes,
+            Err( TryLockError::Poisoned( error ) ) => error.into_inner(),
+            Err( TryLockError::WouldBlock ) => return
+        };
+
+        let releases = releases_of( &codes );
+        if !releases.is_empty() && emit_frame_into( fp, &releases ).is_ok() {
+            codes.clear();
+        }
+    }
+}
+
+fn releases_of( codes: &BTreeSet< u16 > ) -> Vec< InputEventBody > {
+    codes.iter().map( |&code| InputEventBody::KeyRelease( Key::from( code ) ) ).collect()
+}
+
+struct PanicRegistration {
+    fp: Arc< File >,
+    pressed_keys: Arc< PressedKeys >
+}
+
+static PANIC_REGISTRY: Mutex< Vec< PanicRegistration > > = Mutex::new( Vec::new() );
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+fn panic_registry() -> MutexGuard< 'static, Vec< PanicRegistration > > {
+    PANIC_REGISTRY.lock().unwrap_or_else( |error| error.into_inner() )
+}
+
+fn release_registered_keys() {
+    // A panic while the registry is locked would deadlock here.
+    let registry = match PANIC_REGISTRY.try_lock() {
+        Ok( registry ) => registry,
+        Err( _ ) => return
+    };
+
+    for registration in registry.iter() {
+        registration.pressed_keys.try_release_all_into( &registration.fp );
+    }
+}
+
+/// Makes a panic anywhere in the process release the keys of a given device.
+///
+/// The first registration installs a panic hook which runs before the previously set one.
+pub(crate) fn release_on_panic( fp: Arc< File >, pressed_keys: Arc< PressedKeys > ) {
+    INSTALL_PANIC_HOOK.call_once( || {
+        let previous_hook = panic::take_hook();
+        panic::set_hook( Box::new( move |info| {
+            release_registered_keys();
+            previous_hook( info );
+        }));
+    });
+
+    let mut registry = panic_registry();
+    if !registry.iter().any( |registration| Arc::ptr_eq( &registration.pressed_keys, &pressed_keys ) ) {
+        registry.push( PanicRegistration { fp, pressed_keys } );
+    }
+}
+
+pub(crate) fn unregister_from_panic( pressed_keys: &Arc< PressedKeys > ) {
+    panic_registry().retain( |registration| !Arc::ptr_eq( &registration.pressed_keys, pressed_keys ) );
+}
+
+/// Emits events into a `Device` while keeping track of the pressed keys,
+/// and releases them when dropped.
+pub struct KeyGuard< 'a > {
+    device: &'a Device,
+    pressed_keys: PressedKeys
+}
+
+impl< 'a > KeyGuard< 'a > {
+    pub fn new( device: &'a Device ) -> Self {
+        KeyGuard {
+            device,
+            pressed_keys: PressedKeys::default()
+        }
+    }
+
+    /// Emits an event through `Device::emit`.
+    pub fn emit< T >( &self, body: T ) -> Result< (), Error > where T: AsRef< InputEventBody > {
+        let body = body.as_ref();
+        self.device.emit( body )?;
+        self.pressed_keys.track( std::slice::from_ref( body ) );
+        Ok(())
+    }
+
+    /// Returns the keys which were pressed through this guard and not released yet.
+    pub fn pressed_keys( &self ) -> Vec< Key > {
+        self.pressed_keys.keys()
+    }
+
+    /// Releases every key which is still pressed, followed by an `InputEventBody::Flush`.
+    pub fn release_all( &self ) -> Result< (), Error > {
+        let releases = self.pressed_keys.releases();
+        if releases.is_empty() {
+            return Ok(());
+        }
+
+        for release in &releases {
+            self.emit( release )?;
+        }
+
+        self.device.emit( InputEventBody::Flush )
+    }
+}
+
+impl< 'a > Drop for KeyGuard< 'a > {
+    fn drop( &mut self ) {
+        let _ = self.release_all();
+    }
+}
+
+#[test]
+fn test_pressed_keys() {
+    let pressed_keys = PressedKeys::default();
+    pressed_keys.track( &[
+        InputEventBody::KeyPress( Key::LeftShift ),
+        InputEventBody::KeyPress( Key::A ),
+        InputEventBody::Flush,
+        InputEventBody::KeyPress( Key::MouseLeft ),
+        InputEventBody::KeyRelease( Key::A ),
+        InputEventBody::KeyRelease( Key::B )
+    ]);
+
+    assert_eq!( pressed_keys.keys(), vec![ Key::LeftShift, Key::MouseLeft ] );
+    let releases = pressed_keys.releases();
+    assert_eq!( releases, vec![ InputEventBody::KeyRelease( Key::LeftShift ), InputEventBody::KeyRelease( Key::MouseLeft ) ] );
+
+    pressed_keys.track( &releases );
+    assert_eq!( pressed_keys.keys(), vec![] );
+    assert_eq!( pressed_keys.releases(), vec![] );
+}
+
+#[test]
+fn test_pressed_keys_are_not_waited_for_in_panic_hook() {
+    let fp = std::fs::OpenOptions::new().write( true ).open( "/dev/null" ).unwrap();
+    let pressed_keys = PressedKeys::default();
+    pressed_keys.track( &[ InputEventBody::KeyPress( Key::A ) ] );
+
+    let codes = pressed_keys.codes();
+    pressed_keys.try_release_all_into( &fp );
+    drop( codes );
+    assert_eq!( pressed_keys.keys(), vec![ Key::A ] );
+
+    pressed_keys.try_release_all_into( &fp );
+    assert_eq!( pressed_keys.keys(), vec![] );
+}
@@ -1,46 +1,88 @@
 use {
     std::{
+        ffi::{
+            CString
+        },
+        fmt,
         fs::{
             self,
             File
         },
         io::{
-            self
+            self,
+            Write
         },
+        mem,
         os::{
             unix::{
+                ffi::{
+                    OsStrExt
+                },
                 io::{
                     AsRawFd
                 }
             }
         },
+        slice,
+        sync::{
+            Arc
+        },
+        thread,
         time::{
-            Duration
+            Duration,
+            Instant
         },
         path::{
+            Path,
             PathBuf
         }
     },
 
     crate::{
+        error::{
+            Context,
+            Error,
+            ErrorKind
+        },
+        force_feedback::{
+            ForceFeedbackEffect,
+            raw_to_fraction
+        },
         input::{
+            AbsoluteAxisBit,
+            Device,
             DeviceId,
             EventBit,
-            ForceFeedbackEffect,
             InputEventBody,
+            emit_frame_into,
             emit_into
         },
         input_sys::{
+            AbsoluteAxis,
             EventKind,
+            InputProperty,
+            Key,
             RawAbsInfo,
             RawForceFeedbackEffect
         },
+        stuck_keys::{
+            PressedKeys,
+            release_on_panic,
+            unregister_from_panic
+        },
         uinput_sys::{
             self,
+            ABS_CNT,
+            MAX_PHYSICAL_LOCATION_LENGTH,
+            REP_DELAY,
+            REP_PERIOD,
+            UINPUT_MAX_NAME_SIZE,
+            UINPUT_VERSION_DEV_SETUP,
             RawAbsSetup,
             RawForceFeedbackErase,
             RawForceFeedbackUpload,
-            RawDeviceSetup
+            RawDeviceSetup,
+            RawUserDevice
         },
         utils::{
             ioctl_get_string
@@ -48,14 +90,45 @@ use {
     }
 };
 
+/// Describes why a `VirtualDeviceBuilder` was rejected before any device was created.
+///
+/// It's reported as an `ErrorKind::InvalidArgument` error; see `Error::device_create_error`.
+/// Failures reported by the kernel are described by the kind of the `Error` itself.
 #[derive(Debug)]
 pub enum DeviceCreateError {
     DeviceNameTooLong,
-    DeviceSetupFailed( nix::Error ),
-    DeviceCreateFailed( nix::Error ),
-    IoFailure( io::Error )
+    DeviceNameContainsNul,
+    PhysicalLocationTooLong,
+    PhysicalLocationContainsNul,
+    UniqueIdTooLong,
+    UniqueIdContainsNul,
+    /// The same axis was specified more than once.
+    DuplicateAbsoluteAxis( AbsoluteAxis ),
+    /// The axis' minimum is bigger than its maximum, or its noise threshold,
+    /// deadzone or resolution are out of range.
+    InvalidAbsoluteAxis( AbsoluteAxisBit ),
+    /// Force feedback bits were specified, but the maximum number of effects is zero.
+    NoForceFeedbackEffects
+}
+
+impl fmt::Display for DeviceCreateError {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        match *self {
+            DeviceCreateError::DeviceNameTooLong => write!( fmt, "the device name is longer than {} bytes", UINPUT_MAX_NAME_SIZE - 1 ),
+            DeviceCreateError::DeviceNameContainsNul => write!( fmt, "the device name contains a NUL byte" ),
+            DeviceCreateError::PhysicalLocationTooLong => write!( fmt, "the physical location is longer than {} bytes", MAX_PHYSICAL_LOCATION_LENGTH - 1 ),
+            DeviceCreateError::PhysicalLocationContainsNul => write!( fmt, "the physical location contains a NUL byte" ),
+            DeviceCreateError::UniqueIdTooLong => write!( fmt, "the unique ID is longer than {} bytes", MAX_PHYSICAL_LOCATION_LENGTH - 1 ),
+            DeviceCreateError::UniqueIdContainsNul => write!( fmt, "the unique ID contains a NUL byte" ),
+            DeviceCreateError::DuplicateAbsoluteAxis( axis ) => write!( fmt, "duplicate absolute axis {}", axis ),
+            DeviceCreateError::InvalidAbsoluteAxis( ref bit ) => write!( fmt, "invalid parameters for absolute axis {}: {:?}", bit.axis, bit ),
+            DeviceCreateError::NoForceFeedbackEffects => write!( fmt, "force feedback was enabled, but the maximum number of effects is zero" )
+        }
+    }
 }
 
+impl std::error::Error for DeviceCreateError {}
+
 pub struct ForceFeedbackEffectUpload< 'a > {
     device: &'a VirtualDevice,
     raw: RawForceFeedbackUpload,
@@ -68,28 +141,39 @@ impl< 'a > ForceFeedbackEffectUpload< 'a > {
     }
 
     pub fn raw_effect( &self ) -> RawForceFeedbackEffect {
-        self.raw.effect.clone()
+        self.raw.effect
     }
 
-    pub fn effect( &self ) -> ForceFeedbackEffect {
+    /// Returns the effect which is being uploaded, or `None` if it's of an unknown kind.
+    ///
+    /// The samples of custom periodic waveforms live in the uploader's memory and
+    /// are never available here; in practice the kernel refuses to upload such
+    /// effects into virtual devices in the first place.
+    pub fn effect( &self ) -> Option< ForceFeedbackEffect > {
         unsafe {
             ForceFeedbackEffect::from_raw( &self.raw.effect )
         }
     }
 
-    pub fn complete( mut self ) -> Result< (), nix::Error > {
+    pub fn complete( mut self ) -> Result< (), Error > {
+        self.finish()
+    }
+
+    /// Rejects the request; the process which made it will get `errno` as the error.
+    pub fn fail( mut self, errno: nix::errno::Errno ) -> Result< (), Error > {
+        self.raw.return_value = -(errno as i32);
         self.finish()
     }
 
-    fn finish( &mut self ) -> Result< (), nix::Error > {
+    fn finish( &mut self ) -> Result< (), Error > {
         if self.is_finished {
             return Ok(());
         }
         self.is_finished = true;
 
         unsafe {
-            uinput_sys::end_force_feedback_upload( self.device.fp.as_raw_fd(), &mut self.raw )?;
-        }
+            uinput_sys::end_force_feedback_upload( self.device.fp.as_raw_fd(), &self.raw )
+        }.context( "finish a force feedback upload" )?;
 
         Ok(())
     }
@@ -112,19 +196,25 @@ impl< 'a > ForceFeedbackEffectErase< 'a > {
         self.raw.effect_id as u16
     }
 
-    pub fn complete( mut self ) -> Result< (), nix::Error > {
+    pub fn complete( mut self ) -> Result< (), Error > {
+        self.finish()
+    }
+
+    /// Rejects the request; the process which made it will get `errno` as the error.
+    pub fn fail( mut self, errno: nix::errno::Errno ) -> Result< (), Error > {
+        self.raw.return_value = -(errno as i32);
         self.finish()
     }
 
-    fn finish( &mut self ) -> Result< (), nix::Error > {
+    fn finish( &mut self ) -> Result< (), Error > {
         if self.is_finished {
             return Ok(());
         }
         self.is_finished = true;
 
         unsafe {
-            uinput_sys::end_force_feedback_erase( self.device.fp.as_raw_fd(), &mut self.raw )?;
-        }
+            uinput_sys::end_force_feedback_erase( self.device.fp.as_raw_fd(), &self.raw )
+        }.context( "finish a force feedback erasure" )?;
 
         Ok(())
     }
@@ -149,218 +239,643 @@ pub enum ForceFeedbackRequest< 'a > {
         /// The ID of the effect to disable.
         effect_id: u16
     },
+    /// Sets the global strength of every effect.
+    Gain {
+        /// From `0.0` to `1.0`.
+        gain: f32
+    },
+    /// Sets the strength with which the device should be pulled towards its center.
+    Autocenter {
+        /// From `0.0` (disabled) to `1.0`.
+        strength: f32
+    },
     Other {
         code: u16,
         value: i32
     }
 }
 
-pub struct VirtualDevice {
-    fp: File
+fn unique_id_error( error: nix::Error ) -> Error {
+    // Kernels without `UI_SET_UNIQ` reject it as an unknown ioctl.
+    match error {
+        nix::Error::Sys( errno ) if errno == nix::errno::Errno::ENOTTY || errno == nix::errno::Errno::EINVAL => {
+            Error::new( ErrorKind::NotSupported, "set the unique ID", io::Error::from_raw_os_error( errno as i32 ) )
+        },
+        error => Error::from_nix( "set the unique ID", error )
+    }
 }
 
-impl VirtualDevice {
-    pub fn create< I >( id: DeviceId, name: &str, event_bits: I ) -> Result< Self, DeviceCreateError >
-        where I: IntoIterator< Item = EventBit >
-    {
-        if name.len() >= 80 {
-            return Err( DeviceCreateError::DeviceNameTooLong );
+/// Configures and creates a `VirtualDevice`.
+pub struct VirtualDeviceBuilder {
+    id: DeviceId,
+    name: String,
+    physical_location: Option< String >,
+    unique_id: Option< String >,
+    event_bits: Vec< EventBit >,
+    properties: Vec< InputProperty >,
+    autorepeat: Option< (Duration, Duration) >,
+    force_feedback_effects_max: u32
+}
+
+impl VirtualDeviceBuilder {
+    pub fn new( id: DeviceId, name: &str ) -> Self {
+        VirtualDeviceBuilder {
+            id,
+            name: name.to_owned(),
+            physical_location: None,
+            unique_id: None,
+            event_bits: Vec::new(),
+            properties: Vec::new(),
+            autorepeat: None,
+            force_feedback_effects_max: 1
+        }
+    }
+
+    /// Creates a builder initialized with the name, ID, physical location, properties,
+    /// capabilities, autorepeat settings and force feedback limits of an existing device.
+    ///
+    /// Any of those can be overridden before the virtual device is created.
+    /// The unique ID of the device is not copied, since mainline kernels can't set it.
+    pub fn from_device( device: &Device ) -> Result< Self, Error > {
+        let mut builder = VirtualDeviceBuilder::new( device.id()?, &device.name()? )
+            .event_bits( device.event_bits()? );
+
+        builder.physical_location = match device.physical_location() {
+            Ok( physical_location ) if !physical_location.is_empty() => Some( physical_location ),
+            Ok( _ ) => None,
+            Err( ref error ) if error.raw_os_error() == Some( libc::ENOENT ) => None,
+            Err( error ) => return Err( error )
+        };
+
+        builder.properties = device.properties()?.collect();
+        builder.autorepeat = device.autorepeat()?;
+        if builder.event_bits.iter().any( |bit| matches!( bit, EventBit::ForceFeedback( _ ) ) ) {
+            builder.force_feedback_effects_max = device.max_simultaneous_effects()?.max( 1 );
+        }
+
+        Ok( builder )
+    }
+
+    pub fn id( mut self, id: DeviceId ) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn name( mut self, name: &str ) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    pub fn physical_location( mut self, physical_location: &str ) -> Self {
+        self.physical_location = Some( physical_location.to_owned() );
+        self
+    }
+
+    /// Sets the unique identifier of the device, e.g. its serial number.
+    ///
+    /// Mainline uinput has no way to set it; this only works on kernels patched
+    /// to support `UI_SET_UNIQ`. Elsewhere `create` fails with `ErrorKind::NotSupported`.
+    pub fn unique_id( mut self, unique_id: &str ) -> Self {
+        self.unique_id = Some( unique_id.to_owned() );
+        self
+    }
+
+    pub fn event_bit( mut self, event_bit: EventBit ) -> Self {
+        self.event_bits.push( event_bit );
+        self
+    }
+
+    pub fn event_bits< I >( mut self, event_bits: I ) -> Self where I: IntoIterator< Item = EventBit > {
+        self.event_bits.extend( event_bits );
+        self
+    }
+
+    pub fn property( mut self, property: InputProperty ) -> Self {
+        self.properties.push( property );
+        self
+    }
+
+    /// Makes the kernel automatically repeat the keys which are held down.
+    ///
+    /// The first repeat happens after `delay`, and then every `period`.
+    /// (The kernel's defaults are 250ms and 33ms.)
+    pub fn autorepeat( mut self, delay: Duration, period: Duration ) -> Self {
+        self.autorepeat = Some( (delay, period) );
+        self
+    }
+
+    /// Sets how many force feedback effects can be uploaded into the device at the same time.
+    ///
+    /// The default is one.
+    pub fn force_feedback_effects_max( mut self, count: u32 ) -> Self {
+        self.force_feedback_effects_max = count;
+        self
+    }
+
+    fn validate( &self ) -> Result< (), Error > {
+        let invalid = |error| Err( Error::device_create( "validate the device configuration", error ) );
+        if self.name.len() >= UINPUT_MAX_NAME_SIZE {
+            return invalid( DeviceCreateError::DeviceNameTooLong );
+        }
+
+        if self.name.contains( '\0' ) {
+            return invalid( DeviceCreateError::DeviceNameContainsNul );
+        }
+
+        if let Some( ref physical_location ) = self.physical_location {
+            if physical_location.len() >= MAX_PHYSICAL_LOCATION_LENGTH {
+                return invalid( DeviceCreateError::PhysicalLocationTooLong );
+            }
+
+            if physical_location.contains( '\0' ) {
+                return invalid( DeviceCreateError::PhysicalLocationContainsNul );
+            }
+        }
+
+        if let Some( ref unique_id ) = self.unique_id {
+            if unique_id.len() >= MAX_PHYSICAL_LOCATION_LENGTH {
+                return invalid( DeviceCreateError::UniqueIdTooLong );
+            }
+
+            if unique_id.contains( '\0' ) {
+                return invalid( DeviceCreateError::UniqueIdContainsNul );
+            }
+        }
+
+        let mut axes = Vec::new();
+        let mut has_force_feedback = false;
+        for event_bit in &self.event_bits {
+            match *event_bit {
+                EventBit::AbsoluteAxis( ref descriptor ) => {
+                    if axes.contains( &descriptor.axis ) {
+                        return invalid( DeviceCreateError::DuplicateAbsoluteAxis( descriptor.axis ) );
+                    }
+                    axes.push( descriptor.axis );
+
+                    let range = descriptor.maximum as i64 - descriptor.minimum as i64;
+                    if range < 0 ||
+                        descriptor.noise_threshold < 0 ||
+                        descriptor.deadzone < 0 ||
+                        descriptor.deadzone as i64 > range ||
+                        descriptor.resolution < 0
+                    {
+                        return invalid( DeviceCreateError::InvalidAbsoluteAxis( descriptor.clone() ) );
+                    }
+                },
+                EventBit::ForceFeedback( _ ) => has_force_feedback = true,
+                _ => {}
+            }
+        }
+
+        if has_force_feedback && self.force_feedback_effects_max == 0 {
+            return invalid( DeviceCreateError::NoForceFeedbackEffects );
+        }
+
+        Ok(())
+    }
+
+    /// Sets up the device on kernels older than 4.5 by writing a `uinput_user_dev` into it.
+    ///
+    /// The resolutions of the absolute axes can't be set this way, and their
+    /// initial values are emitted as events once the device is created.
+    fn legacy_setup( &self, mut fp: &File, force_feedback_effects_max: u32 ) -> Result< (), Error > {
+        let mut setup = RawUserDevice {
+            name: [0; UINPUT_MAX_NAME_SIZE],
+            id: self.id.clone().into(),
+            force_feedback_effects_max,
+            absmax: [0; ABS_CNT],
+            absmin: [0; ABS_CNT],
+            absfuzz: [0; ABS_CNT],
+            absflat: [0; ABS_CNT]
+        };
+
+        setup.name[ 0..self.name.len() ].copy_from_slice( self.name.as_bytes() );
+        for event_bit in &self.event_bits {
+            if let EventBit::AbsoluteAxis( ref descriptor ) = *event_bit {
+                let index = descriptor.axis.raw() as usize;
+                if index >= ABS_CNT {
+                    return Err( Error::invalid_argument( "set up the device", format!( "unknown absolute axis {}", descriptor.axis ) ) );
+                }
+
+                setup.absmax[ index ] = descriptor.maximum;
+                setup.absmin[ index ] = descriptor.minimum;
+                setup.absfuzz[ index ] = descriptor.noise_threshold;
+                setup.absflat[ index ] = descriptor.deadzone;
+            }
         }
 
+        let bytes = unsafe {
+            slice::from_raw_parts( &setup as *const RawUserDevice as *const u8, mem::size_of::< RawUserDevice >() )
+        };
+
+        fp.write_all( bytes ).context( "set up the device" )
+    }
+
+    pub fn create( self ) -> Result< VirtualDevice, Error > {
+        self.validate()?;
+
         let fp = fs::OpenOptions::new()
             .read( true )
             .write( true )
             .create( false )
-            .open( "/dev/uinput" ).map_err( DeviceCreateError::IoFailure )?;
+            .open( "/dev/uinput" )
+            .context( "open /dev/uinput" )?;
 
-        let mut has_event_key = false;
-        let mut has_event_relative_axis = false;
-        let mut has_event_absolute_axis = false;
-        let mut has_event_force_feedback = false;
+        let fd = fp.as_raw_fd();
+        let has_device_setup = supports_device_setup( fd );
+        let set_bit = |operation: &'static str, code: u16, callback: unsafe fn( libc::c_int, nix::sys::ioctl::ioctl_param_type ) -> nix::Result< libc::c_int >| {
+            unsafe {
+                callback( fd, code as _ )
+            }.map( |_| () ).context( operation )
+        };
 
-        for event_bit in event_bits {
-            match event_bit {
+        let mut event_kinds = Vec::new();
+        for event_bit in &self.event_bits {
+            let kind = match *event_bit {
                 EventBit::Key( key ) => {
-                    has_event_key = true;
-                    unsafe {
-                        uinput_sys::device_set_key_bit( fp.as_raw_fd(), key.raw() as _ )
-                    }.unwrap();
+                    set_bit( "set a key bit", key.raw(), uinput_sys::device_set_key_bit )?;
+                    EventKind::Key
                 },
                 EventBit::RelativeAxis( axis ) => {
-                    has_event_relative_axis = true;
-                    unsafe {
-                        uinput_sys::device_set_relative_axis_bit( fp.as_raw_fd(), axis.raw() as _ )
-                    }.unwrap();
+                    set_bit( "set a relative axis bit", axis.raw(), uinput_sys::device_set_relative_axis_bit )?;
+                    EventKind::RelativeAxis
                 },
-                EventBit::AbsoluteAxis( descriptor ) => {
-                    has_event_absolute_axis = true;
-                    unsafe {
-                        uinput_sys::device_set_absolute_axis_bit( fp.as_raw_fd(), descriptor.axis.raw() as _ )
-                    }.unwrap();
-
-                    assert!( descriptor.maximum >= descriptor.minimum );
-
-                    let abs_setup = RawAbsSetup {
-                        axis: descriptor.axis.raw(),
-                        info: RawAbsInfo {
-                            value: (descriptor.maximum - descriptor.minimum) / 2 + descriptor.minimum,
-                            minimum: descriptor.minimum,
-                            maximum: descriptor.maximum,
-                            noise_threshold: descriptor.noise_threshold,
-                            deadzone: descriptor.deadzone,
-                            resolution: descriptor.resolution
-                        }
-                    };
+                EventBit::AbsoluteAxis( ref descriptor ) => {
+                    set_bit( "set an absolute axis bit", descriptor.axis.raw(), uinput_sys::device_set_absolute_axis_bit )?;
 
-                    unsafe {
-                        uinput_sys::abs_setup( fp.as_raw_fd(), &abs_setup )
-                    }.unwrap();
+                    // Legacy kernels get the parameters of the axes along with the rest of the setup.
+                    if has_device_setup {
+                        let abs_setup = RawAbsSetup {
+                            axis: descriptor.axis.raw(),
+                            info: raw_abs_info( descriptor )
+                        };
+
+                        unsafe {
+                            uinput_sys::abs_setup( fd, &abs_setup )
+                        }.context( "set up an absolute axis" )?;
+                    }
+
+                    EventKind::AbsoluteAxis
+                },
+                EventBit::Misc( misc ) => {
+                    set_bit( "set a misc bit", misc.raw(), uinput_sys::device_set_misc_bit )?;
+                    EventKind::Misc
+                },
+                EventBit::Switch( switch ) => {
+                    set_bit( "set a switch bit", switch.raw(), uinput_sys::device_set_switch_bit )?;
+                    EventKind::Switch
+                },
+                EventBit::Led( led ) => {
+                    set_bit( "set a LED bit", led.raw(), uinput_sys::device_set_led_bit )?;
+                    EventKind::LED
+                },
+                EventBit::Sound( sound ) => {
+                    set_bit( "set a sound bit", sound.raw(), uinput_sys::device_set_sound_bit )?;
+                    EventKind::Sound
                 },
                 EventBit::ForceFeedback( bit ) => {
-                    has_event_force_feedback = true;
-                    unsafe {
-                        uinput_sys::device_set_force_feedback_bit( fp.as_raw_fd(), bit.raw() as _ )
-                    }.unwrap();
+                    set_bit( "set a force feedback bit", bit.raw(), uinput_sys::device_set_force_feedback_bit )?;
+                    EventKind::ForceFeedback
                 }
+            };
+
+            if !event_kinds.contains( &kind ) {
+                event_kinds.push( kind );
             }
         }
 
-        if has_event_key {
-            unsafe {
-                uinput_sys::device_set_event_bit( fp.as_raw_fd(), EventKind::Key.raw() as _ )
-            }.unwrap();
+        let has_force_feedback = event_kinds.contains( &EventKind::ForceFeedback );
+        if has_force_feedback {
+            // So that we can report when the effects start and stop playing.
+            event_kinds.push( EventKind::ForceFeedbackStatus );
         }
 
-        if has_event_relative_axis {
+        if self.autorepeat.is_some() {
+            event_kinds.push( EventKind::AutoRepeat );
+        }
+
+        for kind in event_kinds {
+            set_bit( "set an event kind bit", kind.raw(), uinput_sys::device_set_event_bit )?;
+        }
+
+        for &property in &self.properties {
             unsafe {
-                uinput_sys::device_set_event_bit( fp.as_raw_fd(), EventKind::RelativeAxis.raw() as _ )
-            }.unwrap();
+                uinput_sys::device_set_property_bit( fd, property.raw() as _ )
+            }.context( "set a property bit" )?;
         }
 
-        if has_event_absolute_axis {
+        if let Some( ref physical_location ) = self.physical_location {
+            let physical_location = CString::new( physical_location.as_str() )
+                .map_err( |_| Error::device_create( "set the physical location", DeviceCreateError::PhysicalLocationContainsNul ) )?;
             unsafe {
-                uinput_sys::device_set_event_bit( fp.as_raw_fd(), EventKind::AbsoluteAxis.raw() as _ )
-            }.unwrap();
+                uinput_sys::device_set_physical_location( fd, physical_location.as_ptr() )
+            }.context( "set the physical location" )?;
         }
 
-        if has_event_force_feedback {
+        if let Some( ref unique_id ) = self.unique_id {
+            let unique_id = CString::new( unique_id.as_str() )
+                .map_err( |_| Error::device_create( "set the unique ID", DeviceCreateError::UniqueIdContainsNul ) )?;
             unsafe {
-                uinput_sys::device_set_event_bit( fp.as_raw_fd(), EventKind::ForceFeedback.raw() as _ )
-            }.unwrap();
+                uinput_sys::device_set_unique_id( fd, unique_id.as_ptr() )
+            }.map_err( unique_id_error )?;
         }
 
-        let mut setup = RawDeviceSetup {
-            id: id.into(),
-            name: [0; 80],
-            force_feedback_effects_max: if has_event_force_feedback { 1 } else { 0 }
-        };
+        let force_feedback_effects_max = if has_force_feedback { self.force_feedback_effects_max } else { 0 };
+        if has_device_setup {
+            let mut setup = RawDeviceSetup {
+                id: self.id.into(),
+                name: [0; UINPUT_MAX_NAME_SIZE],
+                force_feedback_effects_max
+            };
 
-        setup.name[ 0..name.len() ].copy_from_slice( name.as_bytes() );
+            setup.name[ 0..self.name.len() ].copy_from_slice( self.name.as_bytes() );
 
-        unsafe {
-            uinput_sys::device_setup( fp.as_raw_fd(), &setup )
-        }.map_err( DeviceCreateError::DeviceSetupFailed )?;
+            unsafe {
+                uinput_sys::device_setup( fd, &setup )
+            }.context( "set up the device" )?;
+        } else {
+            self.legacy_setup( &fp, force_feedback_effects_max )?;
+        }
 
         unsafe {
-            uinput_sys::device_create( fp.as_raw_fd() )
-        }.map_err( DeviceCreateError::DeviceCreateFailed )?;
+            uinput_sys::device_create( fd )
+        }.context( "create the device" )?;
 
         let device = VirtualDevice {
-            fp
+            fp: Arc::new( fp ),
+            pressed_keys: Arc::new( PressedKeys::default() )
         };
 
+        if let Some( (delay, period) ) = self.autorepeat {
+            let set_autorepeat = |code, value: Duration| {
+                device.emit( InputEventBody::Other {
+                    kind: EventKind::AutoRepeat,
+                    code,
+                    value: value.as_millis().min( i32::MAX as u128 ) as i32
+                })
+            };
+
+            set_autorepeat( REP_DELAY, delay )
+                .and_then( |_| set_autorepeat( REP_PERIOD, period ) )?;
+        }
+
+        if !has_device_setup {
+            let initial_values: Vec< _ > = self.event_bits.iter().filter_map( |event_bit| match *event_bit {
+                EventBit::AbsoluteAxis( ref descriptor ) if descriptor.initial_value != 0 => {
+                    Some( InputEventBody::AbsoluteMove { axis: descriptor.axis, position: descriptor.initial_value } )
+                },
+                _ => None
+            }).collect();
+
+            if !initial_values.is_empty() {
+                device.emit_frame( &initial_values )?;
+            }
+        }
+
         Ok( device )
     }
+}
+
+fn raw_abs_info( descriptor: &AbsoluteAxisBit ) -> RawAbsInfo {
+    RawAbsInfo {
+        value: descriptor.initial_value,
+        minimum: descriptor.minimum,
+        maximum: descriptor.maximum,
+        noise_threshold: descriptor.noise_threshold,
+        deadzone: descriptor.deadzone,
+        resolution: descriptor.resolution
+    }
+}
+
+fn supports_device_setup( fd: libc::c_int ) -> bool {
+    let mut version: libc::c_uint = 0;
+    match unsafe { uinput_sys::get_version( fd, &mut version ) } {
+        Ok( _ ) => version >= UINPUT_VERSION_DEV_SETUP,
+        // Kernels which don't know `UI_GET_VERSION` don't know `UI_DEV_SETUP` either.
+        Err( _ ) => false
+    }
+}
+
+/// The paths under which a `VirtualDevice` is visible.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VirtualDevicePaths {
+    /// The evdev node, e.g. `/dev/input/event5`.
+    pub device_node: PathBuf,
+    /// The directory of the device in sysfs.
+    pub sysfs_path: PathBuf,
+    /// The legacy joystick node, e.g. `/dev/input/js0`, if the kernel created one.
+    pub joystick_node: Option< PathBuf >
+}
+
+const DEFAULT_NODE_TIMEOUT: Duration = Duration::from_secs( 5 );
+
+fn device_node_of( sysfs_path: &Path ) -> PathBuf {
+    // The `uevent` file contains the path of the node relative to `/dev`.
+    if let Ok( uevent ) = fs::read_to_string( sysfs_path.join( "uevent" ) ) {
+        if let Some( name ) = uevent.lines().find_map( |line| line.strip_prefix( "DEVNAME=" ) ) {
+            return Path::new( "/dev" ).join( name );
+        }
+    }
+
+    Path::new( "/dev/input" ).join( sysfs_path.file_name().unwrap_or_default() )
+}
+
+fn is_accessible( path: &Path ) -> bool {
+    let path = match CString::new( path.as_os_str().as_bytes() ) {
+        Ok( path ) => path,
+        Err( _ ) => return false
+    };
+
+    unsafe {
+        libc::access( path.as_ptr(), libc::R_OK | libc::W_OK ) == 0
+    }
+}
+
+/// A virtual input device.
+///
+/// Keys and buttons which are still pressed when the device is dropped are released first.
+pub struct VirtualDevice {
+    fp: Arc< File >,
+    pressed_keys: Arc< PressedKeys >
+}
+
+impl VirtualDevice {
+    pub fn create< I >( id: DeviceId, name: &str, event_bits: I ) -> Result< Self, Error >
+        where I: IntoIterator< Item = EventBit >
+    {
+        VirtualDeviceBuilder::new( id, name )
+            .event_bits( event_bits )
+            .create()
+    }
+
+    /// Creates a virtual device with the same capabilities as an existing device.
+    ///
+    /// Use `VirtualDeviceBuilder::from_device` to override any of them.
+    pub fn clone_from( device: &Device ) -> Result< Self, Error > {
+        VirtualDeviceBuilder::from_device( device )?.create()
+    }
 
-    fn sysname( &self ) -> Result< String, nix::Error > {
+    fn sysname( &self ) -> Result< String, Error > {
         unsafe {
             ioctl_get_string( self.fp.as_raw_fd(), b'U', 44 )
+        }.context( "get the device's sysfs name" )
+    }
+
+    fn find_paths( &self ) -> Result< Option< VirtualDevicePaths >, Error > {
+        let sysfs_path = Path::new( "/sys/class/input" ).join( self.sysname()? );
+        let sysfs_path = fs::canonicalize( &sysfs_path ).context( "find the device in sysfs" )?;
+
+        let mut device_node = None;
+        let mut joystick_node = None;
+        for entry in fs::read_dir( &sysfs_path ).context( "find the device in sysfs" )? {
+            let path = entry.context( "find the device in sysfs" )?.path();
+            let file_name = match path.file_name() {
+                Some( file_name ) => file_name.to_string_lossy().into_owned(),
+                None => continue
+            };
+
+            if file_name.starts_with( "event" ) {
+                device_node = Some( device_node_of( &path ) );
+            } else if file_name.starts_with( "js" ) {
+                joystick_node = Some( device_node_of( &path ) );
+            }
+        }
+
+        let device_node = match device_node {
+            Some( device_node ) => device_node,
+            None => return Ok( None )
+        };
+
+        if !is_accessible( &device_node ) || !joystick_node.as_ref().map( |node: &PathBuf| node.exists() ).unwrap_or( true ) {
+            return Ok( None );
         }
+
+        Ok( Some( VirtualDevicePaths {
+            device_node,
+            sysfs_path,
+            joystick_node
+        }))
     }
 
-    pub fn path( &self ) -> Result< PathBuf, nix::Error > {
-        let sysname = self.sysname()?;
-        let dir_path = format!( "/sys/devices/virtual/input/{}", sysname );
-        let dir = fs::read_dir( dir_path ).unwrap();
-        for entry in dir {
-            let entry = entry.unwrap();
-            if !entry.path().is_dir() {
-                continue;
+    /// Returns the paths under which the device is visible.
+    ///
+    /// The device nodes are created asynchronously by udev, so this waits
+    /// up to `timeout` until they exist and their permissions allow us to open them.
+    pub fn paths( &self, timeout: Duration ) -> Result< VirtualDevicePaths, Error > {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some( paths ) = self.find_paths()? {
+                return Ok( paths );
             }
 
-            let path = entry.path();
-            if path.file_name().unwrap().to_str().unwrap().starts_with( "event" ) {
-                let output: PathBuf = "/dev/input".into();
-                let output = output.join( path.file_name().unwrap() );
-                return Ok( output );
+            if Instant::now() >= deadline {
+                let error = io::Error::new( io::ErrorKind::TimedOut, "the device node wasn't created in time" );
+                return Err( Error::from_io( "wait for the device node", error ) );
             }
+
+            thread::sleep( Duration::from_millis( 10 ) );
         }
+    }
 
-        unreachable!();
-    }
-
-    pub fn poll_force_feedback( &self, timeout: Option< Duration > ) -> Result< Option< ForceFeedbackRequest >, io::Error > {
-        match crate::input::read_raw_input_event( &self.fp, timeout )? {
-            Some( event ) if event.kind == uinput_sys::EV_UINPUT && event.code == uinput_sys::UI_FF_UPLOAD => {
-                let upload = unsafe {
-                    let mut upload = std::mem::MaybeUninit::< RawForceFeedbackUpload >::zeroed();
-                    (*upload.as_mut_ptr()).request_id = event.value as u32;
-                    uinput_sys::begin_force_feedback_upload( self.fp.as_raw_fd(), upload.as_mut_ptr() )
-                        .map_err( |error| io::Error::new( io::ErrorKind::Other, error ) )?;
-                    upload.assume_init()
-                };
-
-                let request = ForceFeedbackRequest::Upload( ForceFeedbackEffectUpload {
-                    device: self,
-                    raw: upload,
-                    is_finished: false
-                });
-
-                Ok( Some( request ) )
-            },
-            Some( event ) if event.kind == uinput_sys::EV_UINPUT && event.code == uinput_sys::UI_FF_ERASE => {
-                let mut erase = RawForceFeedbackErase {
-                    request_id: event.value as u32,
-                    return_value: 0,
-                    effect_id: 0
-                };
-
-                unsafe {
-                    uinput_sys::begin_force_feedback_erase( self.fp.as_raw_fd(), &mut erase )
-                        .map_err( |error| io::Error::new( io::ErrorKind::Other, error ) )?;
-                }
+    /// Returns the path of the device's evdev node, e.g. `/dev/input/event5`.
+    ///
+    /// Waits up to five seconds for the node to be created.
+    pub fn path( &self ) -> Result< PathBuf, Error > {
+        Ok( self.paths( DEFAULT_NODE_TIMEOUT )?.device_node )
+    }
+
+    /// Opens the device for reading, just as any other process would see it.
+    ///
+    /// This is mostly useful for tests which need to read back what they emit.
+    pub fn open_as_device( &self ) -> Result< Device, Error > {
+        Device::open( self.path()? )
+    }
+
+    /// Waits for the next force feedback request sent to the device.
+    ///
+    /// Any other events written into the device (e.g. LEDs) are skipped;
+    /// `None` is returned only when the `timeout` passes without a request.
+    pub fn poll_force_feedback( &self, timeout: Option< Duration > ) -> Result< Option< ForceFeedbackRequest< '_ > >, Error > {
+        let deadline = timeout.map( |timeout| Instant::now() + timeout );
+        loop {
+            let timeout = deadline.map( |deadline| deadline.saturating_duration_since( Instant::now() ) );
+            let event = match crate::input::read_raw_input_event( &self.fp, timeout )? {
+                Some( event ) => event,
+                // A spurious wakeup; we don't want to report it as a timeout.
+                None if deadline.map_or( true, |deadline| Instant::now() < deadline ) => continue,
+                None => return Ok( None )
+            };
+
+            match event {
+                event if event.kind == uinput_sys::EV_UINPUT && event.code == uinput_sys::UI_FF_UPLOAD => {
+                    let upload = unsafe {
+                        let mut upload = std::mem::MaybeUninit::< RawForceFeedbackUpload >::zeroed();
+                        (*upload.as_mut_ptr()).request_id = event.value as u32;
+                        uinput_sys::begin_force_feedback_upload( self.fp.as_raw_fd(), upload.as_mut_ptr() )
+                            .context( "begin a force feedback upload" )?;
+                        upload.assume_init()
+                    };
+
+                    let request = ForceFeedbackRequest::Upload( ForceFeedbackEffectUpload {
+                        device: self,
+                        raw: upload,
+                        is_finished: false
+                    });
 
-                let request = ForceFeedbackRequest::Erase( ForceFeedbackEffectErase {
-                    device: self,
-                    raw: erase,
-                    is_finished: false
-                });
-
-                Ok( Some( request ) )
-            },
-            Some( event ) if event.kind == EventKind::ForceFeedback.raw() => {
-                let event = if event.code < crate::input_sys::FF_GAIN {
-                    if event.value > 0 {
-                        ForceFeedbackRequest::Enable {
-                            effect_id: event.code as _,
-                            cycle_count: event.value
+                    return Ok( Some( request ) );
+                },
+                event if event.kind == uinput_sys::EV_UINPUT && event.code == uinput_sys::UI_FF_ERASE => {
+                    let mut erase = RawForceFeedbackErase {
+                        request_id: event.value as u32,
+                        return_value: 0,
+                        effect_id: 0
+                    };
+
+                    unsafe {
+                        uinput_sys::begin_force_feedback_erase( self.fp.as_raw_fd(), &mut erase )
+                            .context( "begin a force feedback erasure" )?;
+                    }
+
+                    let request = ForceFeedbackRequest::Erase( ForceFeedbackEffectErase {
+                        device: self,
+                        raw: erase,
+                        is_finished: false
+                    });
+
+                    return Ok( Some( request ) );
+                },
+                event if event.kind == EventKind::ForceFeedback.raw() => {
+                    let event = if event.code < crate::input_sys::FF_GAIN {
+                        if event.value > 0 {
+                            ForceFeedbackRequest::Enable {
+                                effect_id: event.code as _,
+                                cycle_count: event.value
+                            }
+                        } else {
+                            ForceFeedbackRequest::Disable {
+                                effect_id: event.code as _
+                            }
+                        }
+                    } else if event.code == crate::input_sys::FF_GAIN {
+                        ForceFeedbackRequest::Gain {
+                            gain: raw_to_fraction( event.value )
+                        }
+                    } else if event.code == crate::input_sys::FF_AUTOCENTER {
+                        ForceFeedbackRequest::Autocenter {
+                            strength: raw_to_fraction( event.value )
                         }
                     } else {
-                        ForceFeedbackRequest::Disable {
-                            effect_id: event.code as _
+                        ForceFeedbackRequest::Other {
+                            code: event.code,
+                            value: event.value
                         }
-                    }
-                } else {
-                    ForceFeedbackRequest::Other {
-                        code: event.code,
-                        value: event.value
-                    }
-                };
+                    };
 
-                Ok( Some( event ) )
-            },
-            Some( event ) => unreachable!( "unknown event kind: {}", event.kind ),
-            _ => Ok( None )
+                    return Ok( Some( event ) );
+                },
+                // Other kinds of events (e.g. LEDs) aren't force feedback requests.
+                _ => continue
+            }
         }
     }
 
@@ -371,15 +886,179 @@ impl VirtualDevice {
     ///
     /// The events are buffered and will not be sent immediately;
     /// you need to send `InputEventBody::Flush` to flush them.
-    pub fn emit< T >( &self, body: T ) -> Result< (), io::Error > where T: AsRef< InputEventBody > {
-        emit_into( &self.fp, body )
+    pub fn emit< T >( &self, body: T ) -> Result< (), Error > where T: AsRef< InputEventBody > {
+        let body = body.as_ref();
+        emit_into( &self.fp, body )?;
+        self.pressed_keys.track( slice::from_ref( body ) );
+        Ok(())
+    }
+
+    /// Emits a whole frame of events at once.
+    ///
+    /// An `InputEventBody::Flush` is automatically appended, and the whole
+    /// frame is written with a single system call.
+    pub fn emit_frame( &self, bodies: &[InputEventBody] ) -> Result< (), Error > {
+        emit_frame_into( &self.fp, bodies )?;
+        self.pressed_keys.track( bodies );
+        Ok(())
+    }
+
+    /// Returns the keys and buttons which this device reports as pressed.
+    pub fn pressed_keys( &self ) -> Vec< Key > {
+        self.pressed_keys.keys()
+    }
+
+    /// Releases every key and button which is still pressed in a single frame.
+    pub fn release_all( &self ) -> Result< (), Error > {
+        let releases = self.pressed_keys.releases();
+        if releases.is_empty() {
+            return Ok(());
+        }
+
+        self.emit_frame( &releases )
+    }
+
+    /// Makes a panic anywhere in the process release the keys of this device,
+    /// so that they aren't stuck if the process dies while holding them.
+    ///
+    /// This installs a process-wide panic hook, which calls the previously installed one afterwards.
+    pub fn release_all_on_panic( &self ) {
+        release_on_panic( self.fp.clone(), self.pressed_keys.clone() );
+    }
+
+    /// Starts building a frame of events which will be emitted with `emit_frame`
+    /// once the builder is flushed or dropped.
+    pub fn frame( &self ) -> FrameBuilder< '_ > {
+        FrameBuilder {
+            device: self,
+            bodies: Vec::new()
+        }
+    }
+}
+
+/// Collects events into a single frame.
+///
+/// The frame is emitted when the builder is dropped; use `flush` to
+/// emit it explicitly and get the result.
+pub struct FrameBuilder< 'a > {
+    device: &'a VirtualDevice,
+    bodies: Vec< InputEventBody >
+}
+
+impl< 'a > FrameBuilder< 'a > {
+    pub fn push< T >( &mut self, body: T ) -> &mut Self where T: AsRef< InputEventBody > {
+        self.bodies.push( body.as_ref().clone() );
+        self
+    }
+
+    pub fn flush( mut self ) -> Result< (), Error > {
+        self.flush_pending()
+    }
+
+    fn flush_pending( &mut self ) -> Result< (), Error > {
+        if self.bodies.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.device.emit_frame( &self.bodies );
+        self.bodies.clear();
+        result
+    }
+}
+
+impl< 'a > Drop for FrameBuilder< 'a > {
+    fn drop( &mut self ) {
+        let _ = self.flush_pending();
     }
 }
 
 impl Drop for VirtualDevice {
     fn drop( &mut self ) {
+        unregister_from_panic( &self.pressed_keys );
+        let _ = self.release_all();
         unsafe {
             let _ = uinput_sys::device_destroy( self.fp.as_raw_fd() );
         }
     }
 }
+
+#[cfg(test)]
+fn test_builder() -> VirtualDeviceBuilder {
+    VirtualDeviceBuilder::new( DeviceId { bus: crate::Bus::Virtual, vendor: 1, product: 1, version: 1 }, "test" )
+}
+
+#[cfg(test)]
+fn test_axis( axis: crate::AbsoluteAxis, minimum: i32, maximum: i32 ) -> EventBit {
+    EventBit::AbsoluteAxis( crate::AbsoluteAxisBit {
+        axis,
+        initial_value: 0,
+        minimum,
+        maximum,
+        noise_threshold: 0,
+        deadzone: 0,
+        resolution: 0
+    })
+}
+
+#[test]
+fn test_builder_validation() {
+    let is_invalid = |builder: VirtualDeviceBuilder| {
+        builder.validate().map_err( |error| error.kind() ).err() == Some( ErrorKind::InvalidArgument )
+    };
+    let reason = |builder: VirtualDeviceBuilder| {
+        builder.validate().unwrap_err().device_create_error().map( |error| error.to_string() )
+    };
+
+    assert!( test_builder().validate().is_ok() );
+
+    let name = "x".repeat( UINPUT_MAX_NAME_SIZE );
+    assert!( is_invalid( VirtualDeviceBuilder::new( test_builder().id, &name ) ) );
+    assert!( is_invalid( test_builder().physical_location( "usb\0" ) ) );
+    assert!( is_invalid( test_builder().event_bit( test_axis( crate::AbsoluteAxis::X, 10, -10 ) ) ) );
+    assert!( is_invalid(
+        test_builder()
+            .event_bit( test_axis( crate::AbsoluteAxis::X, -10, 10 ) )
+            .event_bit( test_axis( crate::AbsoluteAxis::X, 0, 1 ) )
+    ));
+    assert!( is_invalid(
+        test_builder()
+            .event_bit( EventBit::ForceFeedback( crate::ForceFeedback::Rumble ) )
+            .force_feedback_effects_max( 0 )
+    ));
+
+    assert_eq!( reason( test_builder().unique_id( "a\0" ) ), Some( DeviceCreateError::UniqueIdContainsNul.to_string() ) );
+    assert_eq!(
+        reason( test_builder().event_bit( test_axis( crate::AbsoluteAxis::Y, 0, 1 ) ).event_bit( test_axis( crate::AbsoluteAxis::Y, 0, 1 ) ) ),
+        Some( "duplicate absolute axis Y".to_owned() )
+    );
+}
+
+#[test]
+fn test_unique_id_error() {
+    let error = unique_id_error( nix::Error::Sys( nix::errno::Errno::ENOTTY ) );
+    assert_eq!( error.kind(), ErrorKind::NotSupported );
+    assert_eq!( error.operation(), "set the unique ID" );
+    assert_eq!( unique_id_error( nix::Error::Sys( nix::errno::Errno::EINVAL ) ).kind(), ErrorKind::NotSupported );
+    assert_eq!( unique_id_error( nix::Error::Sys( nix::errno::Errno::EFAULT ) ).kind(), ErrorKind::Other );
+}
+
+#[test]
+fn test_full_range_absolute_axis() {
+    let descriptor = AbsoluteAxisBit {
+        axis: crate::AbsoluteAxis::X,
+        initial_value: 0,
+        minimum: i32::MIN,
+        maximum: i32::MAX,
+        noise_threshold: 0,
+        deadzone: 0,
+        resolution: 0
+    };
+
+    let info = raw_abs_info( &descriptor );
+    assert_eq!( info.value, 0 );
+    assert_eq!( (info.minimum, info.maximum), (i32::MIN, i32::MAX) );
+    assert!( test_builder().event_bit( EventBit::AbsoluteAxis( descriptor.clone() ) ).validate().is_ok() );
+
+    let info = raw_abs_info( &AbsoluteAxisBit { initial_value: 128, minimum: 0, maximum: 255, ..descriptor } );
+    assert_eq!( info.value, 128 );
+}
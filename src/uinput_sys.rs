@@ -8,13 +8,38 @@ use {
     }
 };
 
+pub const UINPUT_MAX_NAME_SIZE: usize = 80;
+
+// The kernel doesn't enforce any particular limit, but this is what `ioctl_get_string` can read back.
+pub const MAX_PHYSICAL_LOCATION_LENGTH: usize = 1024;
+
+pub const REP_DELAY: u16 = 0x00;
+pub const REP_PERIOD: u16 = 0x01;
+
 #[repr(C)]
 pub struct RawDeviceSetup {
     pub id: RawDeviceId,
-    pub name: [u8; 80],
+    pub name: [u8; UINPUT_MAX_NAME_SIZE],
     pub force_feedback_effects_max: u32
 }
 
+pub const ABS_CNT: usize = 0x40;
+
+// The version in which `UI_DEV_SETUP` and `UI_ABS_SETUP` were introduced (Linux 4.5).
+pub const UINPUT_VERSION_DEV_SETUP: libc::c_uint = 5;
+
+// The legacy way of setting up a device, written directly into the file descriptor.
+#[repr(C)]
+pub struct RawUserDevice {
+    pub name: [u8; UINPUT_MAX_NAME_SIZE],
+    pub id: RawDeviceId,
+    pub force_feedback_effects_max: u32,
+    pub absmax: [i32; ABS_CNT],
+    pub absmin: [i32; ABS_CNT],
+    pub absfuzz: [i32; ABS_CNT],
+    pub absflat: [i32; ABS_CNT]
+}
+
 #[repr(C)]
 pub struct RawAbsSetup {
     pub axis: u16,
@@ -40,13 +65,30 @@ ioctl_write_ptr!( device_setup, b'U', 3, RawDeviceSetup );
 ioctl_write_ptr!( abs_setup, b'U', 4, RawAbsSetup );
 ioctl_none!( device_create, b'U', 1 );
 ioctl_none!( device_destroy, b'U', 2 );
+ioctl_read!( get_version, b'U', 45, libc::c_uint );
 
 ioctl_write_int!( device_set_event_bit, b'U', 100 );
 ioctl_write_int!( device_set_key_bit, b'U', 101 );
 ioctl_write_int!( device_set_relative_axis_bit, b'U', 102 );
 ioctl_write_int!( device_set_absolute_axis_bit, b'U', 103 );
 ioctl_write_int!( device_set_misc_bit, b'U', 104 );
+ioctl_write_int!( device_set_led_bit, b'U', 105 );
+ioctl_write_int!( device_set_sound_bit, b'U', 106 );
 ioctl_write_int!( device_set_force_feedback_bit, b'U', 107 );
+ioctl_write_int!( device_set_switch_bit, b'U', 109 );
+ioctl_write_int!( device_set_property_bit, b'U', 110 );
+
+// These take a pointer to a NUL terminated string directly.
+pub unsafe fn device_set_physical_location( fd: libc::c_int, phys: *const libc::c_char ) -> nix::Result< libc::c_int > {
+    let result = libc::ioctl( fd, request_code_write!( b'U', 108, std::mem::size_of::< *const libc::c_char >() ), phys );
+    nix::errno::Errno::result( result )
+}
+
+// `UI_SET_UNIQ` isn't in mainline uinput; only some patched kernels support it.
+pub unsafe fn device_set_unique_id( fd: libc::c_int, uniq: *const libc::c_char ) -> nix::Result< libc::c_int > {
+    let result = libc::ioctl( fd, request_code_write!( b'U', 111, std::mem::size_of::< *const libc::c_char >() ), uniq );
+    nix::errno::Errno::result( result )
+}
 
 ioctl_readwrite!( begin_force_feedback_upload, b'U', 200, RawForceFeedbackUpload );
 ioctl_write_ptr!( end_force_feedback_upload, b'U', 201, RawForceFeedbackUpload );
@@ -56,3 +98,8 @@ ioctl_write_ptr!( end_force_feedback_erase, b'U', 203, RawForceFeedbackErase );
 pub const EV_UINPUT: u16 = 0x0101;
 pub const UI_FF_UPLOAD: u16 = 1;
 pub const UI_FF_ERASE: u16 = 2;
+
+#[test]
+fn test_raw_user_device_size() {
+    assert_eq!( std::mem::size_of::< RawUserDevice >(), 1116 );
+}
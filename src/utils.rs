@@ -16,6 +16,6 @@ pub unsafe fn ioctl_get_string( fd: RawFd, ioctl_id: u8, ioctl_seq: usize ) -> R
     let result = libc::ioctl( fd, request_code_read!( ioctl_id, ioctl_seq, buffer.len() ), buffer.as_mut_ptr() );
     let length = nix::errno::Errno::result( result )?;
 
-    let name = String::from_utf8_lossy( &buffer[ 0..(length as usize) - 1 ] );
+    let name = String::from_utf8_lossy( &buffer[ 0..(length as usize).saturating_sub( 1 ) ] );
     Ok( name.into_owned() )
 }
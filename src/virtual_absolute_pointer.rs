@@ -0,0 +1,175 @@
+use {
+    std::{
+        thread,
+        time::{
+            Duration
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            AbsoluteAxisBit,
+            DeviceId,
+            EventBit,
+            InputEventBody
+        },
+        input_sys::{
+            AbsoluteAxis,
+            Bus,
+            InputProperty,
+            Key
+        },
+        scroll_wheel::{
+            ScrollWheels
+        },
+        uinput::{
+            VirtualDevice,
+            VirtualDeviceBuilder
+        }
+    }
+};
+
+fn scale( position: f64, screen_length: u32, minimum: i32, maximum: i32 ) -> i32 {
+    if screen_length <= 1 || !position.is_finite() {
+        return minimum;
+    }
+
+    let ratio = (position / (screen_length - 1) as f64).clamp( 0.0, 1.0 );
+    (minimum as f64 + ratio * (maximum as f64 - minimum as f64)).round() as i32
+}
+
+/// A virtual pointer which is placed at absolute screen coordinates, e.g. for remote desktop tools.
+///
+/// Compositors map its axes across all of the outputs, the same way as they do for tablets,
+/// so the screen size given here should be the size of the whole desktop.
+pub struct VirtualAbsolutePointer {
+    device: VirtualDevice,
+    screen_size: (u32, u32),
+    minimum: i32,
+    maximum: i32,
+    wheels: ScrollWheels,
+    click_delay: Duration
+}
+
+impl VirtualAbsolutePointer {
+    /// Creates a new absolute pointer with axes going from 0 to 65535.
+    pub fn create( screen_size: (u32, u32) ) -> Result< Self, Error > {
+        let id = DeviceId {
+            bus: Bus::Virtual,
+            vendor: 0,
+            product: 0,
+            version: 1
+        };
+
+        let builder = VirtualDeviceBuilder::new( id, "linux-input virtual absolute pointer" );
+        VirtualAbsolutePointer::from_builder( builder, screen_size, 0, 0xffff, InputProperty::Pointer )
+    }
+
+    /// Creates a new absolute pointer from a given builder.
+    ///
+    /// Its axes go from `minimum` to `maximum`. The `property` should be either `InputProperty::Pointer`
+    /// or `InputProperty::Direct`; the latter marks it as a device whose coordinates map directly
+    /// onto the screen, though udev and libinput still classify it by its axes and buttons.
+    pub fn from_builder( builder: VirtualDeviceBuilder, screen_size: (u32, u32), minimum: i32, maximum: i32, property: InputProperty ) -> Result< Self, Error > {
+        if minimum >= maximum {
+            let message = format!( "the axis minimum must be less than its maximum, got {}..{}", minimum, maximum );
+            return Err( Error::invalid_argument( "create an absolute pointer", message ) );
+        }
+
+        let axis = |axis| EventBit::AbsoluteAxis( AbsoluteAxisBit {
+            axis,
+            initial_value: minimum,
+            minimum,
+            maximum,
+            noise_threshold: 0,
+            deadzone: 0,
+            resolution: 0
+        });
+
+        let device = builder
+            .event_bit( axis( AbsoluteAxis::X ) )
+            .event_bit( axis( AbsoluteAxis::Y ) )
+            .event_bits( [ Key::MouseLeft, Key::MouseRight, Key::MouseMiddle ].iter().map( |&key| EventBit::Key( key ) ) )
+            .event_bits( ScrollWheels::AXES.iter().map( |&axis| EventBit::RelativeAxis( axis ) ) )
+            .property( property )
+            .create()?;
+
+        Ok( VirtualAbsolutePointer {
+            device,
+            screen_size,
+            minimum,
+            maximum,
+            wheels: ScrollWheels::default(),
+            click_delay: Duration::from_millis( 50 )
+        })
+    }
+
+    pub fn device( &self ) -> &VirtualDevice {
+        &self.device
+    }
+
+    /// Sets the size of the screen, e.g. after its resolution was changed.
+    pub fn set_screen_size( &mut self, screen_size: (u32, u32) ) {
+        self.screen_size = screen_size;
+    }
+
+    pub fn set_click_delay( &mut self, delay: Duration ) {
+        self.click_delay = delay;
+    }
+
+    /// Moves the pointer to the given screen coordinates, in pixels.
+    ///
+    /// Coordinates outside of the screen are clamped to its edges.
+    pub fn move_to( &self, x: f64, y: f64 ) -> Result< (), Error > {
+        self.device.emit_frame( &[
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: scale( x, self.screen_size.0, self.minimum, self.maximum ) },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: scale( y, self.screen_size.1, self.minimum, self.maximum ) }
+        ])
+    }
+
+    pub fn press( &self, button: Key ) -> Result< (), Error > {
+        self.device.emit_frame( &[ InputEventBody::KeyPress( button ) ] )
+    }
+
+    pub fn release( &self, button: Key ) -> Result< (), Error > {
+        self.device.emit_frame( &[ InputEventBody::KeyRelease( button ) ] )
+    }
+
+    pub fn click( &self, button: Key ) -> Result< (), Error > {
+        self.press( button )?;
+        thread::sleep( self.click_delay );
+        self.release( button )
+    }
+
+    /// Scrolls vertically by the given amount of high resolution units; see `VirtualMouse::scroll`.
+    pub fn scroll( &mut self, amount: i32 ) -> Result< (), Error > {
+        self.wheels.scroll( &self.device, amount )
+    }
+
+    pub fn scroll_horizontally( &mut self, amount: i32 ) -> Result< (), Error > {
+        self.wheels.scroll_horizontally( &self.device, amount )
+    }
+}
+
+#[test]
+fn test_absolute_pointer_scale() {
+    assert_eq!( scale( 0.0, 1920, 0, 0xffff ), 0 );
+    assert_eq!( scale( 1919.0, 1920, 0, 0xffff ), 0xffff );
+    assert_eq!( scale( 959.5, 1920, 0, 0xffff ), 0x8000 );
+    assert_eq!( scale( -10.0, 1920, 0, 0xffff ), 0 );
+    assert_eq!( scale( 5000.0, 1920, 0, 0xffff ), 0xffff );
+    assert_eq!( scale( 10.0, 0, 0, 0xffff ), 0 );
+
+    assert_eq!( scale( 0.0, 1920, -32768, 32767 ), -32768 );
+    assert_eq!( scale( 1919.0, 1920, -32768, 32767 ), 32767 );
+    assert_eq!( scale( 959.5, 1920, -32767, 32767 ), 0 );
+    assert_eq!( scale( 10.0, 0, 100, 200 ), 100 );
+
+    assert_eq!( scale( 0.0, 1920, i32::MIN, i32::MAX ), i32::MIN );
+    assert_eq!( scale( 1919.0, 1920, i32::MIN, i32::MAX ), i32::MAX );
+
+    assert_eq!( scale( f64::NAN, 1920, 100, 200 ), 100 );
+    assert_eq!( scale( f64::INFINITY, 1920, 100, 200 ), 100 );
+}
@@ -0,0 +1,276 @@
+use {
+    std::{
+        collections::{
+            HashSet
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            AbsoluteAxisBit,
+            DeviceId,
+            EventBit,
+            InputEventBody
+        },
+        input_sys::{
+            AbsoluteAxis,
+            Bus,
+            ForceFeedback,
+            Key
+        },
+        uinput::{
+            VirtualDevice,
+            VirtualDeviceBuilder
+        }
+    }
+};
+
+/// The controllers which a virtual gamepad can mimic.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum GamepadPreset {
+    Xbox360,
+    XboxOne,
+    DualShock4,
+    Generic
+}
+
+const XBOX_BUTTONS: &[Key] = &[
+    Key::PadSouth, Key::PadEast, Key::PadNorth, Key::PadWest,
+    Key::ShoulderLeft, Key::ShoulderRight,
+    Key::Select, Key::Start, Key::HomeButton,
+    Key::StickLeft, Key::StickRight
+];
+
+const FULL_BUTTONS: &[Key] = &[
+    Key::PadSouth, Key::PadEast, Key::PadNorth, Key::PadWest,
+    Key::ShoulderLeft, Key::ShoulderRight, Key::ShoulderLeftLower, Key::ShoulderRightLower,
+    Key::Select, Key::Start, Key::HomeButton,
+    Key::StickLeft, Key::StickRight
+];
+
+impl GamepadPreset {
+    pub fn id( self ) -> DeviceId {
+        let (bus, vendor, product, version) = match self {
+            GamepadPreset::Xbox360 => (Bus::USB, 0x045e, 0x028e, 0x0114),
+            GamepadPreset::XboxOne => (Bus::USB, 0x045e, 0x02ea, 0x0301),
+            GamepadPreset::DualShock4 => (Bus::USB, 0x054c, 0x09cc, 0x8111),
+            GamepadPreset::Generic => (Bus::Virtual, 0, 0, 1)
+        };
+
+        DeviceId { bus, vendor, product, version }
+    }
+
+    /// The name under which the kernel driver of the real controller registers it.
+    pub fn name( self ) -> &'static str {
+        match self {
+            GamepadPreset::Xbox360 => "Microsoft X-Box 360 pad",
+            GamepadPreset::XboxOne => "Microsoft X-Box One S pad",
+            GamepadPreset::DualShock4 => "Sony Interactive Entertainment Wireless Controller",
+            GamepadPreset::Generic => "linux-input virtual gamepad"
+        }
+    }
+
+    pub fn buttons( self ) -> &'static [Key] {
+        match self {
+            GamepadPreset::Xbox360 | GamepadPreset::XboxOne => XBOX_BUTTONS,
+            GamepadPreset::DualShock4 | GamepadPreset::Generic => FULL_BUTTONS
+        }
+    }
+
+    fn stick_axis( self, axis: AbsoluteAxis ) -> AbsoluteAxisBit {
+        match self {
+            GamepadPreset::DualShock4 => AbsoluteAxisBit {
+                axis,
+                initial_value: 128,
+                minimum: 0,
+                maximum: 255,
+                noise_threshold: 0,
+                deadzone: 0,
+                resolution: 0
+            },
+            _ => AbsoluteAxisBit {
+                axis,
+                initial_value: 0,
+                minimum: -32768,
+                maximum: 32767,
+                noise_threshold: 16,
+                deadzone: 128,
+                resolution: 0
+            }
+        }
+    }
+
+    fn trigger_axis( self, axis: AbsoluteAxis ) -> AbsoluteAxisBit {
+        let maximum = match self {
+            GamepadPreset::XboxOne => 1023,
+            _ => 255
+        };
+
+        AbsoluteAxisBit {
+            axis,
+            initial_value: 0,
+            minimum: 0,
+            maximum,
+            noise_threshold: 0,
+            deadzone: 0,
+            resolution: 0
+        }
+    }
+
+    fn hat_axis( axis: AbsoluteAxis ) -> AbsoluteAxisBit {
+        AbsoluteAxisBit {
+            axis,
+            initial_value: 0,
+            minimum: -1,
+            maximum: 1,
+            noise_threshold: 0,
+            deadzone: 0,
+            resolution: 0
+        }
+    }
+
+    /// Returns a builder for a virtual device which mimics this controller, including rumble support.
+    pub fn builder( self ) -> VirtualDeviceBuilder {
+        let axes = vec![
+            self.stick_axis( AbsoluteAxis::X ),
+            self.stick_axis( AbsoluteAxis::Y ),
+            self.stick_axis( AbsoluteAxis::RX ),
+            self.stick_axis( AbsoluteAxis::RY ),
+            self.trigger_axis( AbsoluteAxis::Z ),
+            self.trigger_axis( AbsoluteAxis::RZ ),
+            GamepadPreset::hat_axis( AbsoluteAxis::Hat0X ),
+            GamepadPreset::hat_axis( AbsoluteAxis::Hat0Y )
+        ];
+
+        VirtualDeviceBuilder::new( self.id(), self.name() )
+            .event_bits( self.buttons().iter().map( |&key| EventBit::Key( key ) ) )
+            .event_bits( axes.into_iter().map( EventBit::AbsoluteAxis ) )
+            .event_bit( EventBit::ForceFeedback( ForceFeedback::Rumble ) )
+            .force_feedback_effects_max( 16 )
+    }
+}
+
+fn scale( value: f32, axis: &AbsoluteAxisBit, is_centered: bool ) -> i32 {
+    let ratio = if is_centered {
+        (value.clamp( -1.0, 1.0 ) + 1.0) / 2.0
+    } else {
+        value.clamp( 0.0, 1.0 )
+    };
+
+    axis.minimum + (ratio as f64 * (axis.maximum - axis.minimum) as f64).round() as i32
+}
+
+/// The whole state of a gamepad, independent of the preset.
+///
+/// The sticks go from -1.0 to 1.0, where negative values point left and up,
+/// and the triggers go from 0.0 to 1.0.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GamepadState {
+    pub left_stick: (f32, f32),
+    pub right_stick: (f32, f32),
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    /// The d-pad; -1, 0 or 1 on each axis, where negative values point left and up.
+    pub dpad: (i32, i32),
+    pub buttons: HashSet< Key >
+}
+
+impl GamepadState {
+    /// Returns the events which bring a device created from the given preset into this state.
+    ///
+    /// Every axis and button is included; the kernel drops the ones which didn't change.
+    pub fn to_frame( &self, preset: GamepadPreset ) -> Vec< InputEventBody > {
+        let stick = |axis, value| InputEventBody::AbsoluteMove { axis, position: scale( value, &preset.stick_axis( axis ), true ) };
+        let trigger = |axis, value| InputEventBody::AbsoluteMove { axis, position: scale( value, &preset.trigger_axis( axis ), false ) };
+
+        let mut bodies = vec![
+            stick( AbsoluteAxis::X, self.left_stick.0 ),
+            stick( AbsoluteAxis::Y, self.left_stick.1 ),
+            stick( AbsoluteAxis::RX, self.right_stick.0 ),
+            stick( AbsoluteAxis::RY, self.right_stick.1 ),
+            trigger( AbsoluteAxis::Z, self.left_trigger ),
+            trigger( AbsoluteAxis::RZ, self.right_trigger ),
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Hat0X, position: self.dpad.0.signum() },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Hat0Y, position: self.dpad.1.signum() }
+        ];
+
+        bodies.extend( preset.buttons().iter().map( |&key| {
+            if self.buttons.contains( &key ) {
+                InputEventBody::KeyPress( key )
+            } else {
+                InputEventBody::KeyRelease( key )
+            }
+        }));
+
+        bodies
+    }
+}
+
+/// A virtual gamepad which mimics one of the `GamepadPreset`s.
+pub struct VirtualGamepad {
+    device: VirtualDevice,
+    preset: GamepadPreset
+}
+
+impl VirtualGamepad {
+    pub fn create( preset: GamepadPreset ) -> Result< Self, Error > {
+        VirtualGamepad::from_builder( preset.builder(), preset )
+    }
+
+    /// Creates a gamepad from a builder which was returned by `GamepadPreset::builder`, e.g. after renaming it.
+    pub fn from_builder( builder: VirtualDeviceBuilder, preset: GamepadPreset ) -> Result< Self, Error > {
+        Ok( VirtualGamepad {
+            device: builder.create()?,
+            preset
+        })
+    }
+
+    /// The underlying device, e.g. to receive rumble requests with `poll_force_feedback`.
+    pub fn device( &self ) -> &VirtualDevice {
+        &self.device
+    }
+
+    pub fn preset( &self ) -> GamepadPreset {
+        self.preset
+    }
+
+    /// Emits the given state as a single frame.
+    pub fn emit_state( &self, state: &GamepadState ) -> Result< (), Error > {
+        self.device.emit_frame( &state.to_frame( self.preset ) )
+    }
+}
+
+#[test]
+fn test_gamepad_state_to_frame() {
+    let mut state = GamepadState {
+        left_stick: (-1.0, 1.0),
+        right_trigger: 1.0,
+        dpad: (0, -1),
+        ..GamepadState::default()
+    };
+    state.buttons.insert( Key::PadSouth );
+
+    let frame = state.to_frame( GamepadPreset::Xbox360 );
+    assert_eq!( &frame[ ..8 ], &[
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: -32768 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: 32767 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::RX, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::RY, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Z, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::RZ, position: 255 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Hat0X, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Hat0Y, position: -1 }
+    ]);
+    assert_eq!( frame[ 8 ], InputEventBody::KeyPress( Key::PadSouth ) );
+    assert_eq!( frame[ 9 ], InputEventBody::KeyRelease( Key::PadEast ) );
+    assert_eq!( frame.len(), 8 + XBOX_BUTTONS.len() );
+
+    let frame = GamepadState::default().to_frame( GamepadPreset::DualShock4 );
+    assert_eq!( frame[ 0 ], InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: 128 } );
+    assert_eq!( frame.len(), 8 + FULL_BUTTONS.len() );
+
+    let frame = state.to_frame( GamepadPreset::XboxOne );
+    assert_eq!( frame[ 5 ], InputEventBody::AbsoluteMove { axis: AbsoluteAxis::RZ, position: 1023 } );
+}
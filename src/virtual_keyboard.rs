@@ -0,0 +1,415 @@
+use {
+    std::{
+        thread,
+        time::{
+            Duration
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            DeviceId,
+            EventBit,
+            InputEventBody
+        },
+        input_sys::{
+            Bus,
+            Key
+        },
+        uinput::{
+            VirtualDevice,
+            VirtualDeviceBuilder
+        }
+    }
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum KeyboardLayout {
+    Us,
+    Uk,
+    De,
+    Fr
+}
+
+/// A key along with the modifiers which have to be held while it's pressed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Keystroke {
+    pub key: Key,
+    pub shift: bool,
+    /// The right Alt, which acts as AltGr on most non-US layouts.
+    pub altgr: bool,
+    pub ctrl: bool
+}
+
+impl Keystroke {
+    pub fn new( key: Key ) -> Self {
+        Keystroke {
+            key,
+            shift: false,
+            altgr: false,
+            ctrl: false
+        }
+    }
+
+    fn modifiers( &self ) -> impl DoubleEndedIterator< Item = Key > {
+        vec![
+            (self.ctrl, Key::LeftCtrl),
+            (self.shift, Key::LeftShift),
+            (self.altgr, Key::RightAlt)
+        ].into_iter().filter( |&(is_held, _)| is_held ).map( |(_, key)| key )
+    }
+}
+
+const SHIFT: u8 = 1;
+const ALTGR: u8 = 2;
+
+type LayoutTable = &'static [(char, Key, u8)];
+
+const COMMON: LayoutTable = &[
+    (' ', Key::Space, 0), ('\n', Key::Enter, 0), ('\t', Key::Tab, 0)
+];
+
+const US: LayoutTable = &[
+    ('`', Key::Grave, 0), ('~', Key::Grave, SHIFT),
+    ('!', Key::Digit1, SHIFT), ('@', Key::Digit2, SHIFT), ('#', Key::Digit3, SHIFT), ('$', Key::Digit4, SHIFT),
+    ('%', Key::Digit5, SHIFT), ('^', Key::Digit6, SHIFT), ('&', Key::Digit7, SHIFT), ('*', Key::Digit8, SHIFT),
+    ('(', Key::Digit9, SHIFT), (')', Key::Digit0, SHIFT),
+    ('-', Key::Minus, 0), ('_', Key::Minus, SHIFT), ('=', Key::Equal, 0), ('+', Key::Equal, SHIFT),
+    ('[', Key::LeftBrace, 0), ('{', Key::LeftBrace, SHIFT), (']', Key::RightBrace, 0), ('}', Key::RightBrace, SHIFT),
+    (';', Key::Semicolon, 0), (':', Key::Semicolon, SHIFT), ('\'', Key::Apostrophe, 0), ('"', Key::Apostrophe, SHIFT),
+    ('\\', Key::Backslash, 0), ('|', Key::Backslash, SHIFT),
+    (',', Key::Comma, 0), ('<', Key::Comma, SHIFT), ('.', Key::Dot, 0), ('>', Key::Dot, SHIFT),
+    ('/', Key::Slash, 0), ('?', Key::Slash, SHIFT)
+];
+
+const UK: LayoutTable = &[
+    ('`', Key::Grave, 0), ('¬', Key::Grave, SHIFT), ('¦', Key::Grave, ALTGR),
+    ('!', Key::Digit1, SHIFT), ('"', Key::Digit2, SHIFT), ('£', Key::Digit3, SHIFT), ('$', Key::Digit4, SHIFT),
+    ('€', Key::Digit4, ALTGR), ('%', Key::Digit5, SHIFT), ('^', Key::Digit6, SHIFT), ('&', Key::Digit7, SHIFT),
+    ('*', Key::Digit8, SHIFT), ('(', Key::Digit9, SHIFT), (')', Key::Digit0, SHIFT),
+    ('-', Key::Minus, 0), ('_', Key::Minus, SHIFT), ('=', Key::Equal, 0), ('+', Key::Equal, SHIFT),
+    ('[', Key::LeftBrace, 0), ('{', Key::LeftBrace, SHIFT), (']', Key::RightBrace, 0), ('}', Key::RightBrace, SHIFT),
+    (';', Key::Semicolon, 0), (':', Key::Semicolon, SHIFT), ('\'', Key::Apostrophe, 0), ('@', Key::Apostrophe, SHIFT),
+    ('#', Key::Backslash, 0), ('~', Key::Backslash, SHIFT), ('\\', Key::Key102nd, 0), ('|', Key::Key102nd, SHIFT),
+    (',', Key::Comma, 0), ('<', Key::Comma, SHIFT), ('.', Key::Dot, 0), ('>', Key::Dot, SHIFT),
+    ('/', Key::Slash, 0), ('?', Key::Slash, SHIFT)
+];
+
+// `^`, `´` and `` ` `` are dead keys on this layout, so they're left out.
+const DE: LayoutTable = &[
+    ('°', Key::Grave, SHIFT),
+    ('!', Key::Digit1, SHIFT), ('"', Key::Digit2, SHIFT), ('²', Key::Digit2, ALTGR), ('§', Key::Digit3, SHIFT),
+    ('³', Key::Digit3, ALTGR), ('$', Key::Digit4, SHIFT), ('%', Key::Digit5, SHIFT), ('&', Key::Digit6, SHIFT),
+    ('/', Key::Digit7, SHIFT), ('{', Key::Digit7, ALTGR), ('(', Key::Digit8, SHIFT), ('[', Key::Digit8, ALTGR),
+    (')', Key::Digit9, SHIFT), (']', Key::Digit9, ALTGR), ('=', Key::Digit0, SHIFT), ('}', Key::Digit0, ALTGR),
+    ('ß', Key::Minus, 0), ('?', Key::Minus, SHIFT), ('\\', Key::Minus, ALTGR),
+    ('@', Key::Q, ALTGR), ('€', Key::E, ALTGR),
+    ('ü', Key::LeftBrace, 0), ('Ü', Key::LeftBrace, SHIFT), ('+', Key::RightBrace, 0), ('*', Key::RightBrace, SHIFT),
+    ('~', Key::RightBrace, ALTGR),
+    ('ö', Key::Semicolon, 0), ('Ö', Key::Semicolon, SHIFT), ('ä', Key::Apostrophe, 0), ('Ä', Key::Apostrophe, SHIFT),
+    ('#', Key::Backslash, 0), ('\'', Key::Backslash, SHIFT),
+    ('<', Key::Key102nd, 0), ('>', Key::Key102nd, SHIFT), ('|', Key::Key102nd, ALTGR), ('µ', Key::M, ALTGR),
+    (',', Key::Comma, 0), (';', Key::Comma, SHIFT), ('.', Key::Dot, 0), (':', Key::Dot, SHIFT),
+    ('-', Key::Slash, 0), ('_', Key::Slash, SHIFT)
+];
+
+// `~`, `` ` ``, `^` (on its own key) and `¨` are dead keys on this layout, so they're left out.
+const FR: LayoutTable = &[
+    ('²', Key::Grave, 0),
+    ('&', Key::Digit1, 0), ('é', Key::Digit2, 0), ('"', Key::Digit3, 0), ('#', Key::Digit3, ALTGR),
+    ('\'', Key::Digit4, 0), ('{', Key::Digit4, ALTGR), ('(', Key::Digit5, 0), ('[', Key::Digit5, ALTGR),
+    ('-', Key::Digit6, 0), ('|', Key::Digit6, ALTGR), ('è', Key::Digit7, 0), ('_', Key::Digit8, 0),
+    ('\\', Key::Digit8, ALTGR), ('ç', Key::Digit9, 0), ('^', Key::Digit9, ALTGR), ('à', Key::Digit0, 0),
+    ('@', Key::Digit0, ALTGR),
+    (')', Key::Minus, 0), ('°', Key::Minus, SHIFT), (']', Key::Minus, ALTGR),
+    ('=', Key::Equal, 0), ('+', Key::Equal, SHIFT), ('}', Key::Equal, ALTGR),
+    ('€', Key::E, ALTGR), ('$', Key::RightBrace, 0), ('£', Key::RightBrace, SHIFT), ('¤', Key::RightBrace, ALTGR),
+    ('ù', Key::Apostrophe, 0), ('%', Key::Apostrophe, SHIFT), ('*', Key::Backslash, 0), ('µ', Key::Backslash, SHIFT),
+    ('<', Key::Key102nd, 0), ('>', Key::Key102nd, SHIFT),
+    (',', Key::M, 0), ('?', Key::M, SHIFT), (';', Key::Comma, 0), ('.', Key::Comma, SHIFT),
+    (':', Key::Dot, 0), ('/', Key::Dot, SHIFT), ('!', Key::Slash, 0), ('§', Key::Slash, SHIFT)
+];
+
+const DIGIT_KEYS: [Key; 10] = [
+    Key::Digit0, Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4,
+    Key::Digit5, Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9
+];
+
+const LETTER_KEYS: [Key; 26] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z
+];
+
+impl KeyboardLayout {
+    fn table( self ) -> LayoutTable {
+        match self {
+            KeyboardLayout::Us => US,
+            KeyboardLayout::Uk => UK,
+            KeyboardLayout::De => DE,
+            KeyboardLayout::Fr => FR
+        }
+    }
+
+    fn letter_key( self, letter: char ) -> Key {
+        match (self, letter) {
+            (KeyboardLayout::De, 'y') => Key::Z,
+            (KeyboardLayout::De, 'z') => Key::Y,
+            (KeyboardLayout::Fr, 'a') => Key::Q,
+            (KeyboardLayout::Fr, 'q') => Key::A,
+            (KeyboardLayout::Fr, 'w') => Key::Z,
+            (KeyboardLayout::Fr, 'z') => Key::W,
+            (KeyboardLayout::Fr, 'm') => Key::Semicolon,
+            _ => LETTER_KEYS[ (letter as u8 - b'a') as usize ]
+        }
+    }
+
+    /// Returns the keystroke which types a given character, or `None` if it's not on the layout.
+    ///
+    /// Assumes that Caps Lock is off.
+    pub fn keystroke( self, character: char ) -> Option< Keystroke > {
+        if let Some( &(_, key, modifiers) ) = COMMON.iter().chain( self.table() ).find( |&&(entry, _, _)| entry == character ) {
+            return Some( Keystroke {
+                key,
+                shift: modifiers & SHIFT != 0,
+                altgr: modifiers & ALTGR != 0,
+                ctrl: false
+            });
+        }
+
+        if let Some( digit ) = character.to_digit( 10 ) {
+            return Some( Keystroke {
+                shift: self == KeyboardLayout::Fr,
+                ..Keystroke::new( DIGIT_KEYS[ digit as usize ] )
+            });
+        }
+
+        if character.is_ascii_alphabetic() {
+            return Some( Keystroke {
+                shift: character.is_ascii_uppercase(),
+                ..Keystroke::new( self.letter_key( character.to_ascii_lowercase() ) )
+            });
+        }
+
+        None
+    }
+
+    /// Returns the keystrokes which enter a given character through its hexadecimal code point
+    /// with Ctrl+Shift+U, as understood by GTK and IBus.
+    pub fn unicode_input_keystrokes( self, character: char ) -> Option< Vec< Keystroke > > {
+        let mut keystrokes = vec![ Keystroke {
+            shift: true,
+            ctrl: true,
+            ..Keystroke::new( Key::U )
+        }];
+
+        for digit in format!( "{:x}", character as u32 ).chars() {
+            keystrokes.push( self.keystroke( digit )? );
+        }
+
+        keystrokes.push( Keystroke::new( Key::Space ) );
+        Some( keystrokes )
+    }
+}
+
+/// A virtual keyboard which can type text.
+pub struct VirtualKeyboard {
+    device: VirtualDevice,
+    layout: KeyboardLayout,
+    key_delay: Duration,
+    unicode_input: bool
+}
+
+impl VirtualKeyboard {
+    /// Creates a new virtual keyboard.
+    ///
+    /// The `layout` must match the layout which is configured for the keyboard
+    /// on the receiving side, or the wrong characters will be typed.
+    pub fn create( layout: KeyboardLayout ) -> Result< Self, Error > {
+        let id = DeviceId {
+            bus: Bus::Virtual,
+            vendor: 0,
+            product: 0,
+            version: 1
+        };
+
+        VirtualKeyboard::from_builder( VirtualDeviceBuilder::new( id, "linux-input virtual keyboard" ), layout )
+    }
+
+    /// Creates a new virtual keyboard from a given builder, e.g. to customize its name.
+    ///
+    /// Every standard keyboard key is added to the builder.
+    pub fn from_builder( builder: VirtualDeviceBuilder, layout: KeyboardLayout ) -> Result< Self, Error > {
+        let device = builder
+            .event_bits( (1..0x80).map( |code| EventBit::Key( Key::from( code ) ) ) )
+            .create()?;
+
+        Ok( VirtualKeyboard {
+            device,
+            layout,
+            key_delay: Duration::from_millis( 10 ),
+            unicode_input: true
+        })
+    }
+
+    pub fn device( &self ) -> &VirtualDevice {
+        &self.device
+    }
+
+    pub fn layout( &self ) -> KeyboardLayout {
+        self.layout
+    }
+
+    pub fn set_layout( &mut self, layout: KeyboardLayout ) {
+        self.layout = layout;
+    }
+
+    /// Sets how long to wait after every key press and release.
+    ///
+    /// Some applications drop keys when they're typed too fast. The default is 10ms.
+    pub fn set_key_delay( &mut self, delay: Duration ) {
+        self.key_delay = delay;
+    }
+
+    /// Sets whether characters which aren't on the layout are typed with Ctrl+Shift+U.
+    ///
+    /// This is enabled by default. When disabled `type_text` fails on such characters.
+    pub fn set_unicode_input( &mut self, is_enabled: bool ) {
+        self.unicode_input = is_enabled;
+    }
+
+    pub fn press( &self, keystroke: Keystroke ) -> Result< (), Error > {
+        let mut bodies: Vec< _ > = keystroke.modifiers().map( InputEventBody::KeyPress ).collect();
+        bodies.push( InputEventBody::KeyPress( keystroke.key ) );
+        self.device.emit_frame( &bodies )
+    }
+
+    pub fn release( &self, keystroke: Keystroke ) -> Result< (), Error > {
+        let mut bodies = vec![ InputEventBody::KeyRelease( keystroke.key ) ];
+        bodies.extend( keystroke.modifiers().rev().map( InputEventBody::KeyRelease ) );
+        self.device.emit_frame( &bodies )
+    }
+
+    /// Presses and releases a key.
+    pub fn tap( &self, keystroke: Keystroke ) -> Result< (), Error > {
+        self.press( keystroke )?;
+        thread::sleep( self.key_delay );
+        self.release( keystroke )?;
+        thread::sleep( self.key_delay );
+        Ok(())
+    }
+
+    /// Types a given text.
+    ///
+    /// Nothing is typed if any of the characters can't be typed.
+    pub fn type_text( &self, text: &str ) -> Result< (), Error > {
+        let mut keystrokes = Vec::new();
+        for character in text.chars() {
+            if let Some( keystroke ) = self.layout.keystroke( character ) {
+                keystrokes.push( keystroke );
+                continue;
+            }
+
+            let fallback = if self.unicode_input {
+                self.layout.unicode_input_keystrokes( character )
+            } else {
+                None
+            };
+
+            match fallback {
+                Some( fallback ) => keystrokes.extend( fallback ),
+                None => {
+                    let message = format!( "{:?} can't be typed with the {:?} layout", character, self.layout );
+                    return Err( Error::invalid_argument( "type text", message ) );
+                }
+            }
+        }
+
+        for keystroke in keystrokes {
+            self.tap( keystroke )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn stroke( key: Key, shift: bool, altgr: bool ) -> Option< Keystroke > {
+    Some( Keystroke {
+        key,
+        shift,
+        altgr,
+        ctrl: false
+    })
+}
+
+#[test]
+fn test_keyboard_layout_us() {
+    let layout = KeyboardLayout::Us;
+    assert_eq!( layout.keystroke( 'a' ), stroke( Key::A, false, false ) );
+    assert_eq!( layout.keystroke( 'Z' ), stroke( Key::Z, true, false ) );
+    assert_eq!( layout.keystroke( '1' ), stroke( Key::Digit1, false, false ) );
+    assert_eq!( layout.keystroke( '@' ), stroke( Key::Digit2, true, false ) );
+    assert_eq!( layout.keystroke( '|' ), stroke( Key::Backslash, true, false ) );
+    assert_eq!( layout.keystroke( '\n' ), stroke( Key::Enter, false, false ) );
+    assert_eq!( layout.keystroke( '£' ), None );
+}
+
+#[test]
+fn test_keyboard_layout_uk() {
+    let layout = KeyboardLayout::Uk;
+    assert_eq!( layout.keystroke( '£' ), stroke( Key::Digit3, true, false ) );
+    assert_eq!( layout.keystroke( '@' ), stroke( Key::Apostrophe, true, false ) );
+    assert_eq!( layout.keystroke( '"' ), stroke( Key::Digit2, true, false ) );
+    assert_eq!( layout.keystroke( '\\' ), stroke( Key::Key102nd, false, false ) );
+    assert_eq!( layout.keystroke( '€' ), stroke( Key::Digit4, false, true ) );
+}
+
+#[test]
+fn test_keyboard_layout_de() {
+    let layout = KeyboardLayout::De;
+    assert_eq!( layout.keystroke( 'z' ), stroke( Key::Y, false, false ) );
+    assert_eq!( layout.keystroke( 'Y' ), stroke( Key::Z, true, false ) );
+    assert_eq!( layout.keystroke( 'ß' ), stroke( Key::Minus, false, false ) );
+    assert_eq!( layout.keystroke( 'Ä' ), stroke( Key::Apostrophe, true, false ) );
+    assert_eq!( layout.keystroke( '@' ), stroke( Key::Q, false, true ) );
+    assert_eq!( layout.keystroke( '{' ), stroke( Key::Digit7, false, true ) );
+    assert_eq!( layout.keystroke( '^' ), None );
+}
+
+#[test]
+fn test_keyboard_layout_fr() {
+    let layout = KeyboardLayout::Fr;
+    assert_eq!( layout.keystroke( 'a' ), stroke( Key::Q, false, false ) );
+    assert_eq!( layout.keystroke( 'Q' ), stroke( Key::A, true, false ) );
+    assert_eq!( layout.keystroke( 'm' ), stroke( Key::Semicolon, false, false ) );
+    assert_eq!( layout.keystroke( ',' ), stroke( Key::M, false, false ) );
+    assert_eq!( layout.keystroke( '1' ), stroke( Key::Digit1, true, false ) );
+    assert_eq!( layout.keystroke( 'é' ), stroke( Key::Digit2, false, false ) );
+    assert_eq!( layout.keystroke( '@' ), stroke( Key::Digit0, false, true ) );
+}
+
+#[test]
+fn test_keyboard_layouts_are_unambiguous() {
+    for &layout in &[ KeyboardLayout::Us, KeyboardLayout::Uk, KeyboardLayout::De, KeyboardLayout::Fr ] {
+        let table = layout.table();
+        for (index, &(character, key, modifiers)) in table.iter().enumerate() {
+            assert!( !table[ index + 1.. ].iter().any( |&(other, ..)| other == character ), "{:?}: duplicate {:?}", layout, character );
+            assert!( !table.iter().any( |&(other, other_key, other_modifiers)| other != character && other_key == key && other_modifiers == modifiers ), "{:?}: {:?} collides", layout, character );
+        }
+    }
+}
+
+#[test]
+fn test_unicode_input_keystrokes() {
+    let keystrokes = KeyboardLayout::Fr.unicode_input_keystrokes( '→' ).unwrap();
+    assert_eq!( keystrokes, vec![
+        Keystroke { key: Key::U, shift: true, altgr: false, ctrl: true },
+        Keystroke { key: Key::Digit2, shift: true, altgr: false, ctrl: false },
+        Keystroke { key: Key::Digit1, shift: true, altgr: false, ctrl: false },
+        Keystroke { key: Key::Digit9, shift: true, altgr: false, ctrl: false },
+        Keystroke { key: Key::Digit2, shift: true, altgr: false, ctrl: false },
+        Keystroke::new( Key::Space )
+    ]);
+}
@@ -0,0 +1,243 @@
+use {
+    std::{
+        thread,
+        time::{
+            Duration,
+            Instant
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            DeviceId,
+            EventBit,
+            InputEventBody
+        },
+        input_sys::{
+            Bus,
+            Key,
+            RelativeAxis
+        },
+        scroll_wheel::{
+            ScrollWheels
+        },
+        uinput::{
+            VirtualDevice,
+            VirtualDeviceBuilder
+        }
+    }
+};
+
+/// A path relative to the current position of the pointer.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MousePath {
+    Line {
+        end: (f64, f64)
+    },
+    QuadraticBezier {
+        control: (f64, f64),
+        end: (f64, f64)
+    },
+    CubicBezier {
+        control_1: (f64, f64),
+        control_2: (f64, f64),
+        end: (f64, f64)
+    }
+}
+
+impl MousePath {
+    /// Returns the point at `t`, which goes from 0.0 at the start of the path to 1.0 at its end.
+    pub fn point_at( &self, t: f64 ) -> (f64, f64) {
+        let t = t.clamp( 0.0, 1.0 );
+        let u = 1.0 - t;
+        match *self {
+            MousePath::Line { end } => (end.0 * t, end.1 * t),
+            MousePath::QuadraticBezier { control, end } => (
+                2.0 * u * t * control.0 + t * t * end.0,
+                2.0 * u * t * control.1 + t * t * end.1
+            ),
+            MousePath::CubicBezier { control_1, control_2, end } => (
+                3.0 * u * u * t * control_1.0 + 3.0 * u * t * t * control_2.0 + t * t * t * end.0,
+                3.0 * u * u * t * control_1.1 + 3.0 * u * t * t * control_2.1 + t * t * t * end.1
+            )
+        }
+    }
+}
+
+fn take_whole_pixels( remainder: &mut f64, delta: f64 ) -> i32 {
+    let total = *remainder + delta;
+
+    // Snap to whole pixels so that e.g. ten moves of 0.3 add up to exactly three pixels.
+    let whole = if (total - total.round()).abs() < 1e-9 { total.round() } else { total.trunc() };
+    *remainder = total - whole;
+    whole as i32
+}
+
+/// A virtual mouse with subpixel motion and high resolution scrolling.
+pub struct VirtualMouse {
+    device: VirtualDevice,
+    remainder: (f64, f64),
+    wheels: ScrollWheels,
+    update_rate: u32,
+    click_delay: Duration
+}
+
+impl VirtualMouse {
+    pub fn create() -> Result< Self, Error > {
+        let id = DeviceId {
+            bus: Bus::Virtual,
+            vendor: 0,
+            product: 0,
+            version: 1
+        };
+
+        VirtualMouse::from_builder( VirtualDeviceBuilder::new( id, "linux-input virtual mouse" ) )
+    }
+
+    /// Creates a new virtual mouse from a given builder, e.g. to customize its name.
+    ///
+    /// The mouse buttons and the relative axes are added to the builder.
+    pub fn from_builder( builder: VirtualDeviceBuilder ) -> Result< Self, Error > {
+        let buttons = (0x110..0x118).map( |code| EventBit::Key( Key::from( code ) ) );
+        let axes = [ RelativeAxis::X, RelativeAxis::Y ].iter().chain( ScrollWheels::AXES.iter() );
+
+        let device = builder
+            .event_bits( buttons )
+            .event_bits( axes.map( |&axis| EventBit::RelativeAxis( axis ) ) )
+            .create()?;
+
+        Ok( VirtualMouse {
+            device,
+            remainder: (0.0, 0.0),
+            wheels: ScrollWheels::default(),
+            update_rate: 125,
+            click_delay: Duration::from_millis( 50 )
+        })
+    }
+
+    pub fn device( &self ) -> &VirtualDevice {
+        &self.device
+    }
+
+    /// Sets how many times per second the pointer is moved by `move_along`. The default is 125.
+    pub fn set_update_rate( &mut self, update_rate: u32 ) {
+        self.update_rate = update_rate.max( 1 );
+    }
+
+    /// Sets how long buttons are held by `click`, and the pause between the clicks of `double_click`.
+    pub fn set_click_delay( &mut self, delay: Duration ) {
+        self.click_delay = delay;
+    }
+
+    /// Moves the pointer by the given amount of pixels.
+    ///
+    /// The fractional parts are accumulated and emitted once they add up to whole pixels.
+    pub fn move_by( &mut self, dx: f64, dy: f64 ) -> Result< (), Error > {
+        let dx = take_whole_pixels( &mut self.remainder.0, dx );
+        let dy = take_whole_pixels( &mut self.remainder.1, dy );
+
+        let mut frame = self.device.frame();
+        if dx != 0 {
+            frame.push( InputEventBody::RelativeMove { axis: RelativeAxis::X, delta: dx } );
+        }
+        if dy != 0 {
+            frame.push( InputEventBody::RelativeMove { axis: RelativeAxis::Y, delta: dy } );
+        }
+
+        frame.flush()
+    }
+
+    /// Moves the pointer along a given path over the given duration.
+    pub fn move_along( &mut self, path: MousePath, duration: Duration ) -> Result< (), Error > {
+        let interval = Duration::from_secs( 1 ) / self.update_rate;
+        let steps = ((duration.as_secs_f64() / interval.as_secs_f64()).ceil() as u32).max( 1 );
+        let start = Instant::now();
+        let mut position = (0.0, 0.0);
+
+        for step in 1..=steps {
+            let point = path.point_at( step as f64 / steps as f64 );
+            self.move_by( point.0 - position.0, point.1 - position.1 )?;
+            position = point;
+
+            if step != steps {
+                if let Some( delay ) = (start + interval * step).checked_duration_since( Instant::now() ) {
+                    thread::sleep( delay );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn press( &self, button: Key ) -> Result< (), Error > {
+        self.device.emit_frame( &[ InputEventBody::KeyPress( button ) ] )
+    }
+
+    pub fn release( &self, button: Key ) -> Result< (), Error > {
+        self.device.emit_frame( &[ InputEventBody::KeyRelease( button ) ] )
+    }
+
+    pub fn click( &self, button: Key ) -> Result< (), Error > {
+        self.press( button )?;
+        thread::sleep( self.click_delay );
+        self.release( button )
+    }
+
+    pub fn double_click( &self, button: Key ) -> Result< (), Error > {
+        self.click( button )?;
+        thread::sleep( self.click_delay );
+        self.click( button )
+    }
+
+    /// Holds a button while moving the pointer along a given path.
+    pub fn drag( &mut self, button: Key, path: MousePath, duration: Duration ) -> Result< (), Error > {
+        self.press( button )?;
+        let result = self.move_along( path, duration );
+        let release_result = self.release( button );
+        result.and( release_result )
+    }
+
+    /// Scrolls vertically by the given amount of high resolution units; positive values scroll up.
+    ///
+    /// A single notch is `WHEEL_UNITS_PER_NOTCH` units. The plain wheel axis is moved
+    /// whenever the scrolled amount adds up to whole notches.
+    pub fn scroll( &mut self, amount: i32 ) -> Result< (), Error > {
+        self.wheels.scroll( &self.device, amount )
+    }
+
+    /// Scrolls horizontally by the given amount of high resolution units; positive values scroll right.
+    pub fn scroll_horizontally( &mut self, amount: i32 ) -> Result< (), Error > {
+        self.wheels.scroll_horizontally( &self.device, amount )
+    }
+}
+
+#[test]
+fn test_subpixel_accumulation() {
+    let mut remainder = 0.0;
+    let deltas: Vec< _ > = (0..10).map( |_| take_whole_pixels( &mut remainder, 0.3 ) ).collect();
+    assert_eq!( deltas.iter().sum::< i32 >(), 3 );
+    assert_eq!( &deltas[ ..4 ], &[ 0, 0, 0, 1 ] );
+
+    let mut remainder = 0.0;
+    assert_eq!( take_whole_pixels( &mut remainder, -1.5 ), -1 );
+    assert_eq!( take_whole_pixels( &mut remainder, -0.5 ), -1 );
+    assert_eq!( take_whole_pixels( &mut remainder, 0.2 ), 0 );
+}
+
+#[test]
+fn test_mouse_path() {
+    let line = MousePath::Line { end: (100.0, -50.0) };
+    assert_eq!( line.point_at( 0.0 ), (0.0, 0.0) );
+    assert_eq!( line.point_at( 0.5 ), (50.0, -25.0) );
+    assert_eq!( line.point_at( 2.0 ), (100.0, -50.0) );
+
+    let curve = MousePath::CubicBezier { control_1: (0.0, 100.0), control_2: (100.0, 100.0), end: (100.0, 0.0) };
+    assert_eq!( curve.point_at( 0.0 ), (0.0, 0.0) );
+    assert_eq!( curve.point_at( 0.5 ), (50.0, 75.0) );
+    assert_eq!( curve.point_at( 1.0 ), (100.0, 0.0) );
+
+    let curve = MousePath::QuadraticBezier { control: (50.0, 100.0), end: (100.0, 0.0) };
+    assert_eq!( curve.point_at( 0.5 ), (50.0, 50.0) );
+}
@@ -0,0 +1,328 @@
+use {
+    std::{
+        f32::consts::PI,
+        thread,
+        time::{
+            Duration
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            AbsoluteAxisBit,
+            DeviceId,
+            EventBit,
+            InputEventBody
+        },
+        input_sys::{
+            AbsoluteAxis,
+            Bus,
+            InputProperty,
+            Key
+        },
+        uinput::{
+            VirtualDevice,
+            VirtualDeviceBuilder
+        }
+    }
+};
+
+const PRESSURE_MAXIMUM: i32 = 4095;
+const DISTANCE_MAXIMUM: i32 = 63;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum PenTool {
+    Pen,
+    Eraser
+}
+
+impl PenTool {
+    fn key( self ) -> Key {
+        match self {
+            PenTool::Pen => Key::ToolPen,
+            PenTool::Eraser => Key::ToolRubber
+        }
+    }
+}
+
+/// A single position of the pen while it touches the tablet.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PenSample {
+    pub position: (i32, i32),
+    /// From 0.0 to 1.0.
+    pub pressure: f32,
+    /// The tilt in degrees, from -64 to 63 on each axis.
+    pub tilt: (i32, i32)
+}
+
+/// Returns samples along `path` whose pressure rises and falls like in a natural pen stroke, peaking at `peak_pressure`.
+pub fn pressure_curve( path: &[(i32, i32)], peak_pressure: f32 ) -> Vec< PenSample > {
+    let last = path.len().saturating_sub( 1 ).max( 1 ) as f32;
+    path.iter().enumerate().map( |(index, &position)| {
+        let t = index as f32 / last;
+        PenSample {
+            position,
+            pressure: peak_pressure * (0.2 + 0.8 * (PI * t).sin()),
+            tilt: (0, 0)
+        }
+    }).collect()
+}
+
+#[derive(Default)]
+struct TabletState {
+    tool: Option< PenTool >,
+    is_touching: bool
+}
+
+impl TabletState {
+    fn proximity_in( &mut self, tool: PenTool, position: (i32, i32) ) -> Result< Vec< InputEventBody >, Error > {
+        if self.tool.is_some() {
+            return Err( Error::invalid_argument( "bring the pen into proximity", "a tool is already in proximity".to_owned() ) );
+        }
+
+        self.tool = Some( tool );
+        Ok( vec![
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: position.0 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: position.1 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Distance, position: DISTANCE_MAXIMUM },
+            InputEventBody::KeyPress( tool.key() )
+        ])
+    }
+
+    fn hover( &mut self, position: (i32, i32), distance: i32 ) -> Result< Vec< InputEventBody >, Error > {
+        if self.tool.is_none() || self.is_touching {
+            return Err( Error::invalid_argument( "hover the pen", "the pen must be in proximity and not touching".to_owned() ) );
+        }
+
+        Ok( vec![
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: position.0 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: position.1 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Distance, position: distance.clamp( 0, DISTANCE_MAXIMUM ) }
+        ])
+    }
+
+    fn sample( &mut self, sample: &PenSample ) -> Result< Vec< InputEventBody >, Error > {
+        if self.tool.is_none() {
+            return Err( Error::invalid_argument( "touch the tablet", "the pen isn't in proximity".to_owned() ) );
+        }
+
+        let mut bodies = vec![
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: sample.position.0 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: sample.position.1 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Distance, position: 0 },
+            InputEventBody::AbsoluteMove {
+                axis: AbsoluteAxis::Pressure,
+                position: (sample.pressure.clamp( 0.0, 1.0 ) * PRESSURE_MAXIMUM as f32).round() as i32
+            },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::TiltX, position: sample.tilt.0.clamp( -64, 63 ) },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::TiltY, position: sample.tilt.1.clamp( -64, 63 ) }
+        ];
+
+        if !self.is_touching {
+            self.is_touching = true;
+            bodies.push( InputEventBody::KeyPress( Key::Touch ) );
+        }
+
+        Ok( bodies )
+    }
+
+    fn lift( &mut self ) -> Result< Vec< InputEventBody >, Error > {
+        if !self.is_touching {
+            return Err( Error::invalid_argument( "lift the pen", "the pen isn't touching the tablet".to_owned() ) );
+        }
+
+        self.is_touching = false;
+        Ok( vec![
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Pressure, position: 0 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Distance, position: 1 },
+            InputEventBody::KeyRelease( Key::Touch )
+        ])
+    }
+
+    fn proximity_out( &mut self ) -> Result< Vec< InputEventBody >, Error > {
+        let mut bodies = if self.is_touching { self.lift()? } else { Vec::new() };
+        let tool = match self.tool.take() {
+            Some( tool ) => tool,
+            None => return Err( Error::invalid_argument( "take the pen out of proximity", "the pen isn't in proximity".to_owned() ) )
+        };
+
+        bodies.extend( vec![
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Distance, position: 0 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::TiltX, position: 0 },
+            InputEventBody::AbsoluteMove { axis: AbsoluteAxis::TiltY, position: 0 },
+            InputEventBody::KeyRelease( tool.key() )
+        ]);
+
+        Ok( bodies )
+    }
+}
+
+/// A virtual drawing tablet with a pressure and tilt sensitive pen.
+pub struct VirtualTablet {
+    device: VirtualDevice,
+    state: TabletState,
+    frame_interval: Duration
+}
+
+impl VirtualTablet {
+    /// Creates a new tablet which is 152x95mm large with a resolution of 100 units per millimeter.
+    pub fn create() -> Result< Self, Error > {
+        let id = DeviceId {
+            bus: Bus::Virtual,
+            vendor: 0,
+            product: 0,
+            version: 1
+        };
+
+        VirtualTablet::from_builder( VirtualDeviceBuilder::new( id, "linux-input virtual tablet" ), (15200, 9500), 100 )
+    }
+
+    /// Creates a new tablet from a given builder.
+    ///
+    /// The `maximum` is in device units and the `resolution` in units per millimeter.
+    pub fn from_builder( builder: VirtualDeviceBuilder, maximum: (i32, i32), resolution: i32 ) -> Result< Self, Error > {
+        let axis = |axis, minimum, maximum, resolution| EventBit::AbsoluteAxis( AbsoluteAxisBit {
+            axis,
+            initial_value: 0,
+            minimum,
+            maximum,
+            noise_threshold: 0,
+            deadzone: 0,
+            resolution
+        });
+
+        // The tilt resolution is in units per radian, so 57 makes every unit a degree.
+        let device = builder
+            .event_bit( axis( AbsoluteAxis::X, 0, maximum.0, resolution ) )
+            .event_bit( axis( AbsoluteAxis::Y, 0, maximum.1, resolution ) )
+            .event_bit( axis( AbsoluteAxis::Pressure, 0, PRESSURE_MAXIMUM, 0 ) )
+            .event_bit( axis( AbsoluteAxis::Distance, 0, DISTANCE_MAXIMUM, 0 ) )
+            .event_bit( axis( AbsoluteAxis::TiltX, -64, 63, 57 ) )
+            .event_bit( axis( AbsoluteAxis::TiltY, -64, 63, 57 ) )
+            .event_bits( [ Key::ToolPen, Key::ToolRubber, Key::Touch, Key::Stylus, Key::Stylus2 ].iter().map( |&key| EventBit::Key( key ) ) )
+            .property( InputProperty::Pointer )
+            .create()?;
+
+        Ok( VirtualTablet {
+            device,
+            state: TabletState::default(),
+            frame_interval: Duration::from_millis( 5 )
+        })
+    }
+
+    pub fn device( &self ) -> &VirtualDevice {
+        &self.device
+    }
+
+    /// Sets the time between the samples of a stroke. The default is 5ms.
+    pub fn set_frame_interval( &mut self, interval: Duration ) {
+        self.frame_interval = interval;
+    }
+
+    pub fn proximity_in( &mut self, tool: PenTool, x: i32, y: i32 ) -> Result< (), Error > {
+        let bodies = self.state.proximity_in( tool, (x, y) )?;
+        self.device.emit_frame( &bodies )
+    }
+
+    /// Moves the pen while it's above the tablet; the `distance` goes from 0 to 63.
+    pub fn hover( &mut self, x: i32, y: i32, distance: i32 ) -> Result< (), Error > {
+        let bodies = self.state.hover( (x, y), distance )?;
+        self.device.emit_frame( &bodies )
+    }
+
+    /// Touches the tablet with the pen, or moves it if it's already touching.
+    pub fn touch( &mut self, sample: &PenSample ) -> Result< (), Error > {
+        let bodies = self.state.sample( sample )?;
+        self.device.emit_frame( &bodies )
+    }
+
+    pub fn lift( &mut self ) -> Result< (), Error > {
+        let bodies = self.state.lift()?;
+        self.device.emit_frame( &bodies )
+    }
+
+    /// Takes the pen away from the tablet, lifting it first if necessary.
+    pub fn proximity_out( &mut self ) -> Result< (), Error > {
+        let bodies = self.state.proximity_out()?;
+        self.device.emit_frame( &bodies )
+    }
+
+    /// Presses one of the pen's barrel buttons, `Key::Stylus` or `Key::Stylus2`.
+    pub fn press_button( &self, button: Key ) -> Result< (), Error > {
+        self.device.emit_frame( &[ InputEventBody::KeyPress( button ) ] )
+    }
+
+    pub fn release_button( &self, button: Key ) -> Result< (), Error > {
+        self.device.emit_frame( &[ InputEventBody::KeyRelease( button ) ] )
+    }
+
+    /// Draws a complete stroke: the pen comes into proximity above the first sample,
+    /// touches down, goes through every sample, lifts, and leaves proximity.
+    pub fn draw( &mut self, tool: PenTool, samples: &[PenSample] ) -> Result< (), Error > {
+        let (first, last) = match (samples.first(), samples.last()) {
+            (Some( first ), Some( last )) => (first, last),
+            _ => return Err( Error::invalid_argument( "draw a stroke", "the stroke has no samples".to_owned() ) )
+        };
+
+        self.proximity_in( tool, first.position.0, first.position.1 )?;
+        let result = self.draw_samples( first, last, samples );
+        let proximity_out_result = self.proximity_out();
+        result.and( proximity_out_result )
+    }
+
+    fn draw_samples( &mut self, first: &PenSample, last: &PenSample, samples: &[PenSample] ) -> Result< (), Error > {
+        thread::sleep( self.frame_interval );
+        self.hover( first.position.0, first.position.1, 1 )?;
+        for sample in samples {
+            thread::sleep( self.frame_interval );
+            self.touch( sample )?;
+        }
+
+        thread::sleep( self.frame_interval );
+        self.lift()?;
+        thread::sleep( self.frame_interval );
+        self.hover( last.position.0, last.position.1, DISTANCE_MAXIMUM )
+    }
+
+    /// Draws a stroke along `path` with the pressure following `pressure_curve`.
+    pub fn stroke( &mut self, tool: PenTool, path: &[(i32, i32)], peak_pressure: f32 ) -> Result< (), Error > {
+        self.draw( tool, &pressure_curve( path, peak_pressure ) )
+    }
+}
+
+#[test]
+fn test_pressure_curve() {
+    let samples = pressure_curve( &[ (0, 0), (10, 0), (20, 0) ], 1.0 );
+    assert_eq!( samples.len(), 3 );
+    assert!( (samples[ 0 ].pressure - 0.2).abs() < 0.001 );
+    assert!( (samples[ 1 ].pressure - 1.0).abs() < 0.001 );
+    assert!( (samples[ 2 ].pressure - 0.2).abs() < 0.001 );
+    assert_eq!( samples[ 1 ].position, (10, 0) );
+
+    assert_eq!( pressure_curve( &[ (5, 5) ], 0.5 )[ 0 ].pressure, 0.1 );
+}
+
+#[test]
+fn test_tablet_state() {
+    let mut state = TabletState::default();
+    let sample = PenSample { position: (1, 2), pressure: 0.5, tilt: (10, -100) };
+    assert!( state.sample( &sample ).is_err() );
+    assert!( state.lift().is_err() );
+
+    assert_eq!( state.proximity_in( PenTool::Eraser, (1, 2) ).unwrap().last(), Some( &InputEventBody::KeyPress( Key::ToolRubber ) ) );
+    assert!( state.proximity_in( PenTool::Pen, (1, 2) ).is_err() );
+
+    let bodies = state.sample( &sample ).unwrap();
+    assert!( bodies.contains( &InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Pressure, position: 2048 } ) );
+    assert!( bodies.contains( &InputEventBody::AbsoluteMove { axis: AbsoluteAxis::TiltY, position: -64 } ) );
+    assert_eq!( bodies.last(), Some( &InputEventBody::KeyPress( Key::Touch ) ) );
+    assert!( !state.sample( &sample ).unwrap().contains( &InputEventBody::KeyPress( Key::Touch ) ) );
+    assert!( state.hover( (1, 2), 10 ).is_err() );
+
+    let bodies = state.proximity_out().unwrap();
+    assert!( bodies.contains( &InputEventBody::KeyRelease( Key::Touch ) ) );
+    assert_eq!( bodies.last(), Some( &InputEventBody::KeyRelease( Key::ToolRubber ) ) );
+    assert!( state.proximity_out().is_err() );
+}
@@ -0,0 +1,404 @@
+use {
+    std::{
+        thread,
+        time::{
+            Duration,
+            Instant
+        }
+    },
+    crate::{
+        error::{
+            Error
+        },
+        input::{
+            AbsoluteAxisBit,
+            DeviceId,
+            EventBit,
+            InputEventBody
+        },
+        input_sys::{
+            AbsoluteAxis,
+            Bus,
+            InputProperty,
+            Key
+        },
+        uinput::{
+            VirtualDevice,
+            VirtualDeviceBuilder
+        }
+    }
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum TouchDeviceKind {
+    /// A direct touch device, e.g. a touchscreen.
+    Touchscreen,
+    /// An indirect touch device with an integrated button, e.g. a laptop touchpad.
+    Touchpad
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum TouchUpdate {
+    Down {
+        slot: usize,
+        position: (i32, i32)
+    },
+    Move {
+        slot: usize,
+        position: (i32, i32)
+    },
+    Up {
+        slot: usize
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Touch {
+    tracking_id: i32,
+    position: (i32, i32)
+}
+
+const TOOL_KEYS: [Key; 5] = [
+    Key::ToolFinger,
+    Key::ToolDoubleTap,
+    Key::ToolTripleTap,
+    Key::ToolQuadTap,
+    Key::ToolQuintTap
+];
+
+fn tool_key( touch_count: usize ) -> Option< Key > {
+    match touch_count {
+        0 => None,
+        count => Some( TOOL_KEYS[ count.min( TOOL_KEYS.len() ) - 1 ] )
+    }
+}
+
+/// Tracks the contacts and translates their changes into events of the multitouch protocol B.
+struct TouchState {
+    kind: TouchDeviceKind,
+    maximum: (i32, i32),
+    slots: Vec< Option< Touch > >,
+    next_tracking_id: i32
+}
+
+impl TouchState {
+    fn new( kind: TouchDeviceKind, maximum: (i32, i32), slot_count: usize ) -> Self {
+        TouchState {
+            kind,
+            maximum,
+            slots: vec![ None; slot_count ],
+            next_tracking_id: 0
+        }
+    }
+
+    fn touch_count( &self ) -> usize {
+        self.slots.iter().filter( |touch| touch.is_some() ).count()
+    }
+
+    fn free_slots( &self ) -> impl Iterator< Item = usize > + '_ {
+        self.slots.iter().enumerate().filter( |(_, touch)| touch.is_none() ).map( |(slot, _)| slot )
+    }
+
+    fn clamp( &self, position: (i32, i32) ) -> (i32, i32) {
+        (position.0.clamp( 0, self.maximum.0 ), position.1.clamp( 0, self.maximum.1 ))
+    }
+
+    fn apply( &mut self, updates: &[TouchUpdate] ) -> Result< Vec< InputEventBody >, Error > {
+        let operation = "update the touches";
+        for update in updates {
+            let (slot, should_be_active) = match *update {
+                TouchUpdate::Down { slot, .. } => (slot, false),
+                TouchUpdate::Move { slot, .. } | TouchUpdate::Up { slot } => (slot, true)
+            };
+
+            match self.slots.get( slot ) {
+                None => return Err( Error::invalid_argument( operation, format!( "slot {} doesn't exist", slot ) ) ),
+                Some( touch ) if touch.is_some() != should_be_active => {
+                    let state = if should_be_active { "isn't" } else { "is already" };
+                    return Err( Error::invalid_argument( operation, format!( "slot {} {} touching", slot, state ) ) );
+                },
+                Some( _ ) => {}
+            }
+        }
+
+        let old_count = self.touch_count();
+        let mut bodies = Vec::new();
+        for update in updates {
+            match *update {
+                TouchUpdate::Down { slot, position } => {
+                    let position = self.clamp( position );
+                    let tracking_id = self.next_tracking_id;
+                    self.next_tracking_id = (self.next_tracking_id + 1) & 0xffff;
+                    self.slots[ slot ] = Some( Touch { tracking_id, position } );
+
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: slot as i32 } );
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtTrackingId, position: tracking_id } );
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionX, position: position.0 } );
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionY, position: position.1 } );
+                },
+                TouchUpdate::Move { slot, position } => {
+                    let position = self.clamp( position );
+                    if let Some( touch ) = self.slots[ slot ].as_mut() {
+                        touch.position = position;
+                    }
+
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: slot as i32 } );
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionX, position: position.0 } );
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionY, position: position.1 } );
+                },
+                TouchUpdate::Up { slot } => {
+                    self.slots[ slot ] = None;
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: slot as i32 } );
+                    bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtTrackingId, position: -1 } );
+                }
+            }
+        }
+
+        let new_count = self.touch_count();
+        if old_count == 0 && new_count != 0 {
+            bodies.push( InputEventBody::KeyPress( Key::Touch ) );
+        } else if old_count != 0 && new_count == 0 {
+            bodies.push( InputEventBody::KeyRelease( Key::Touch ) );
+        }
+
+        if self.kind == TouchDeviceKind::Touchpad {
+            let old_tool = tool_key( old_count );
+            let new_tool = tool_key( new_count );
+            if old_tool != new_tool {
+                bodies.extend( old_tool.map( InputEventBody::KeyRelease ) );
+                bodies.extend( new_tool.map( InputEventBody::KeyPress ) );
+            }
+        }
+
+        // The single touch axes follow the first active contact, for clients which don't understand multitouch.
+        if let Some( touch ) = self.slots.iter().flatten().next() {
+            bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: touch.position.0 } );
+            bodies.push( InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: touch.position.1 } );
+        }
+
+        Ok( bodies )
+    }
+}
+
+/// A virtual multitouch touchscreen or touchpad.
+///
+/// The positions are in device units, from zero up to the maximum the device was created with.
+pub struct VirtualTouchDevice {
+    device: VirtualDevice,
+    state: TouchState,
+    frame_interval: Duration
+}
+
+impl VirtualTouchDevice {
+    /// Creates a new device with ten slots, which is about 100x60mm large in case of a touchpad.
+    pub fn create( kind: TouchDeviceKind ) -> Result< Self, Error > {
+        let id = DeviceId {
+            bus: Bus::Virtual,
+            vendor: 0,
+            product: 0,
+            version: 1
+        };
+
+        let name = match kind {
+            TouchDeviceKind::Touchscreen => "linux-input virtual touchscreen",
+            TouchDeviceKind::Touchpad => "linux-input virtual touchpad"
+        };
+
+        VirtualTouchDevice::from_builder( VirtualDeviceBuilder::new( id, name ), kind, (4000, 2400), 40, 10 )
+    }
+
+    /// Creates a new device from a given builder.
+    ///
+    /// The `resolution` is in units per millimeter; libinput uses it to size gestures on touchpads.
+    pub fn from_builder( builder: VirtualDeviceBuilder, kind: TouchDeviceKind, maximum: (i32, i32), resolution: i32, slot_count: usize ) -> Result< Self, Error > {
+        if slot_count == 0 || maximum.0 <= 0 || maximum.1 <= 0 {
+            let message = format!( "invalid touch device geometry: maximum {:?}, {} slots", maximum, slot_count );
+            return Err( Error::invalid_argument( "create a touch device", message ) );
+        }
+
+        let axis = |axis, maximum, resolution| EventBit::AbsoluteAxis( AbsoluteAxisBit {
+            axis,
+            initial_value: 0,
+            minimum: 0,
+            maximum,
+            noise_threshold: 0,
+            deadzone: 0,
+            resolution
+        });
+
+        let mut builder = builder
+            .event_bit( axis( AbsoluteAxis::X, maximum.0, resolution ) )
+            .event_bit( axis( AbsoluteAxis::Y, maximum.1, resolution ) )
+            .event_bit( axis( AbsoluteAxis::MtSlot, slot_count as i32 - 1, 0 ) )
+            .event_bit( axis( AbsoluteAxis::MtTrackingId, 0xffff, 0 ) )
+            .event_bit( axis( AbsoluteAxis::MtPositionX, maximum.0, resolution ) )
+            .event_bit( axis( AbsoluteAxis::MtPositionY, maximum.1, resolution ) )
+            .event_bit( EventBit::Key( Key::Touch ) );
+
+        builder = match kind {
+            TouchDeviceKind::Touchscreen => builder.property( InputProperty::Direct ),
+            TouchDeviceKind::Touchpad => builder
+                .property( InputProperty::Pointer )
+                .property( InputProperty::ButtonPad )
+                .event_bit( EventBit::Key( Key::MouseLeft ) )
+                .event_bits( TOOL_KEYS.iter().map( |&key| EventBit::Key( key ) ) )
+        };
+
+        Ok( VirtualTouchDevice {
+            device: builder.create()?,
+            state: TouchState::new( kind, maximum, slot_count ),
+            frame_interval: Duration::from_millis( 8 )
+        })
+    }
+
+    pub fn device( &self ) -> &VirtualDevice {
+        &self.device
+    }
+
+    /// Sets the time between the frames of gestures. The default is 8ms, which is about what real hardware does.
+    pub fn set_frame_interval( &mut self, interval: Duration ) {
+        self.frame_interval = interval;
+    }
+
+    fn update( &mut self, updates: &[TouchUpdate] ) -> Result< (), Error > {
+        let bodies = self.state.apply( updates )?;
+        self.device.emit_frame( &bodies )
+    }
+
+    /// Puts a finger down at a given position and returns the slot which it occupies.
+    pub fn touch_down( &mut self, x: i32, y: i32 ) -> Result< usize, Error > {
+        let slot = match self.state.free_slots().next() {
+            Some( slot ) => slot,
+            None => return Err( Error::invalid_argument( "put a finger down", "all of the slots are in use".to_owned() ) )
+        };
+
+        self.update( &[ TouchUpdate::Down { slot, position: (x, y) } ] )?;
+        Ok( slot )
+    }
+
+    pub fn touch_move( &mut self, slot: usize, x: i32, y: i32 ) -> Result< (), Error > {
+        self.update( &[ TouchUpdate::Move { slot, position: (x, y) } ] )
+    }
+
+    pub fn touch_up( &mut self, slot: usize ) -> Result< (), Error > {
+        self.update( &[ TouchUpdate::Up { slot } ] )
+    }
+
+    /// Puts down a finger at every start position, moves them all in a straight line
+    /// to their end positions over the given duration, and lifts them.
+    pub fn gesture( &mut self, start: &[(f64, f64)], end: &[(f64, f64)], duration: Duration ) -> Result< (), Error > {
+        if start.len() != end.len() || start.is_empty() {
+            return Err( Error::invalid_argument( "perform a gesture", "every finger needs a start and an end position".to_owned() ) );
+        }
+
+        let slots: Vec< _ > = self.state.free_slots().take( start.len() ).collect();
+        if slots.len() != start.len() {
+            return Err( Error::invalid_argument( "perform a gesture", format!( "not enough free slots for {} fingers", start.len() ) ) );
+        }
+
+        let round = |(x, y): (f64, f64)| (x.round() as i32, y.round() as i32);
+        let downs: Vec< _ > = slots.iter().zip( start ).map( |(&slot, &position)| TouchUpdate::Down { slot, position: round( position ) } ).collect();
+        self.update( &downs )?;
+
+        let steps = ((duration.as_secs_f64() / self.frame_interval.as_secs_f64().max( 0.001 )).ceil() as u32).max( 1 );
+        let started_at = Instant::now();
+        for step in 1..=steps {
+            if let Some( delay ) = (started_at + self.frame_interval * step).checked_duration_since( Instant::now() ) {
+                thread::sleep( delay );
+            }
+
+            let t = step as f64 / steps as f64;
+            let moves: Vec< _ > = slots.iter().zip( start.iter().zip( end ) ).map( |(&slot, (&from, &to))| {
+                let position = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+                TouchUpdate::Move { slot, position: round( position ) }
+            }).collect();
+
+            self.update( &moves )?;
+        }
+
+        thread::sleep( self.frame_interval );
+        let ups: Vec< _ > = slots.iter().map( |&slot| TouchUpdate::Up { slot } ).collect();
+        self.update( &ups )
+    }
+
+    fn finger_spacing( &self ) -> f64 {
+        self.state.maximum.0 as f64 / 8.0
+    }
+
+    /// Moves a given number of fingers side by side around `center` by `delta`.
+    pub fn swipe( &mut self, finger_count: usize, center: (f64, f64), delta: (f64, f64), duration: Duration ) -> Result< (), Error > {
+        let spacing = self.finger_spacing();
+        let start: Vec< _ > = (0..finger_count).map( |index| {
+            let offset = (index as f64 - (finger_count as f64 - 1.0) / 2.0) * spacing;
+            (center.0 + offset, center.1)
+        }).collect();
+
+        let end: Vec< _ > = start.iter().map( |&(x, y)| (x + delta.0, y + delta.1) ).collect();
+        self.gesture( &start, &end, duration )
+    }
+
+    pub fn two_finger_scroll( &mut self, center: (f64, f64), delta: (f64, f64), duration: Duration ) -> Result< (), Error > {
+        self.swipe( 2, center, delta, duration )
+    }
+
+    pub fn three_finger_swipe( &mut self, center: (f64, f64), delta: (f64, f64), duration: Duration ) -> Result< (), Error > {
+        self.swipe( 3, center, delta, duration )
+    }
+
+    /// Moves two fingers horizontally apart from `start_distance` to `end_distance`;
+    /// a growing distance zooms in.
+    pub fn pinch( &mut self, center: (f64, f64), start_distance: f64, end_distance: f64, duration: Duration ) -> Result< (), Error > {
+        let start = [ (center.0 - start_distance / 2.0, center.1), (center.0 + start_distance / 2.0, center.1) ];
+        let end = [ (center.0 - end_distance / 2.0, center.1), (center.0 + end_distance / 2.0, center.1) ];
+        self.gesture( &start, &end, duration )
+    }
+}
+
+#[test]
+fn test_touch_state() {
+    let mut state = TouchState::new( TouchDeviceKind::Touchpad, (1000, 500), 2 );
+    assert_eq!( state.apply( &[ TouchUpdate::Down { slot: 0, position: (10, 2000) } ] ).unwrap(), vec![
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtTrackingId, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionX, position: 10 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionY, position: 500 },
+        InputEventBody::KeyPress( Key::Touch ),
+        InputEventBody::KeyPress( Key::ToolFinger ),
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: 10 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: 500 }
+    ]);
+
+    assert_eq!( state.apply( &[ TouchUpdate::Down { slot: 1, position: (20, 30) } ] ).unwrap(), vec![
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: 1 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtTrackingId, position: 1 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionX, position: 20 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtPositionY, position: 30 },
+        InputEventBody::KeyRelease( Key::ToolFinger ),
+        InputEventBody::KeyPress( Key::ToolDoubleTap ),
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::X, position: 10 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::Y, position: 500 }
+    ]);
+
+    assert!( state.apply( &[ TouchUpdate::Down { slot: 1, position: (0, 0) } ] ).is_err() );
+    assert!( state.apply( &[ TouchUpdate::Move { slot: 2, position: (0, 0) } ] ).is_err() );
+    assert_eq!( state.free_slots().next(), None );
+
+    assert_eq!( state.apply( &[ TouchUpdate::Up { slot: 0 }, TouchUpdate::Up { slot: 1 } ] ).unwrap(), vec![
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: 0 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtTrackingId, position: -1 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtSlot, position: 1 },
+        InputEventBody::AbsoluteMove { axis: AbsoluteAxis::MtTrackingId, position: -1 },
+        InputEventBody::KeyRelease( Key::Touch ),
+        InputEventBody::KeyRelease( Key::ToolDoubleTap )
+    ]);
+
+    assert!( state.apply( &[ TouchUpdate::Up { slot: 0 } ] ).is_err() );
+}
+
+#[test]
+fn test_touchscreen_has_no_tool_keys() {
+    let mut state = TouchState::new( TouchDeviceKind::Touchscreen, (1000, 500), 2 );
+    let bodies = state.apply( &[ TouchUpdate::Down { slot: 0, position: (1, 2) } ] ).unwrap();
+    assert!( !bodies.contains( &InputEventBody::KeyPress( Key::ToolFinger ) ) );
+    assert!( bodies.contains( &InputEventBody::KeyPress( Key::Touch ) ) );
+}
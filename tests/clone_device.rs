@@ -0,0 +1,55 @@
+mod common;
+
+use {
+    linux_input::{
+        AbsoluteAxis,
+        AbsoluteAxisBit,
+        EventBit,
+        InputProperty,
+        Key,
+        Led,
+        VirtualDevice,
+        VirtualDeviceBuilder
+    },
+    std::{
+        time::{
+            Duration
+        }
+    }
+};
+
+#[test]
+fn test_clone_device() {
+    let result = VirtualDeviceBuilder::new( common::test_id(), "linux-input clone test" )
+        .physical_location( "linux-input/clone" )
+        .event_bit( EventBit::Key( Key::A ) )
+        .event_bit( EventBit::Led( Led::CapsLock ) )
+        .event_bit( EventBit::AbsoluteAxis( AbsoluteAxisBit {
+            axis: AbsoluteAxis::X,
+            initial_value: 0,
+            minimum: -100,
+            maximum: 100,
+            noise_threshold: 2,
+            deadzone: 4,
+            resolution: 10
+        }))
+        .property( InputProperty::Direct )
+        .autorepeat( Duration::from_millis( 300 ), Duration::from_millis( 50 ) )
+        .create();
+
+    let original = match common::skip_without_uinput( result ) {
+        Some( device ) => device,
+        None => return
+    };
+
+    let original_device = original.open_as_device().unwrap();
+    let clone = VirtualDevice::clone_from( &original_device ).unwrap();
+    let clone_device = clone.open_as_device().unwrap();
+
+    assert_eq!( clone_device.id().unwrap(), original_device.id().unwrap() );
+    assert_eq!( clone_device.name().unwrap(), "linux-input clone test" );
+    assert_eq!( clone_device.physical_location().unwrap(), "linux-input/clone" );
+    assert_eq!( clone_device.properties().unwrap().collect::< Vec< _ > >(), vec![ InputProperty::Direct ] );
+    assert_eq!( clone_device.autorepeat().unwrap(), Some( (Duration::from_millis( 300 ), Duration::from_millis( 50 )) ) );
+    assert_eq!( clone_device.event_bits().unwrap().collect::< Vec< _ > >(), original_device.event_bits().unwrap().collect::< Vec< _ > >() );
+}
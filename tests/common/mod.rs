@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+
+use {
+    linux_input::{
+        Bus,
+        DeviceId,
+        Error,
+        EventBit,
+        VirtualDevice
+    }
+};
+
+pub fn test_id() -> DeviceId {
+    DeviceId {
+        bus: Bus::Virtual,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 1
+    }
+}
+
+/// Returns the created device, or `None` if `/dev/uinput` can't be opened on this machine.
+pub fn skip_without_uinput< T >( result: Result< T, Error > ) -> Option< T > {
+    match result {
+        Ok( device ) => Some( device ),
+        Err( ref error ) if error.operation() == "open /dev/uinput" => {
+            eprintln!( "skipping test: {}", error );
+            None
+        },
+        Err( error ) => panic!( "failed to create a virtual device: {:?}", error )
+    }
+}
+
+pub fn create_virtual_device( name: &str, event_bits: Vec< EventBit > ) -> Option< VirtualDevice > {
+    skip_without_uinput( VirtualDevice::create( test_id(), name, event_bits ) )
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use {
+    linux_input::{
+        EventBit,
+        InputEventBody,
+        Key,
+        RelativeAxis,
+        VirtualDevice
+    },
+    std::{
+        time::{
+            Duration
+        }
+    }
+};
+
+fn create_virtual_device() -> Option< VirtualDevice > {
+    common::create_virtual_device( "linux-input frame test", vec![
+        EventBit::Key( Key::A ),
+        EventBit::RelativeAxis( RelativeAxis::X ),
+        EventBit::RelativeAxis( RelativeAxis::Y )
+    ])
+}
+
+#[test]
+fn test_emit_frame() {
+    let virtual_device = match create_virtual_device() {
+        Some( device ) => device,
+        None => return
+    };
+
+    let device = virtual_device.open_as_device().unwrap();
+    virtual_device.emit_frame( &[
+        InputEventBody::RelativeMove { axis: RelativeAxis::X, delta: 10 },
+        InputEventBody::RelativeMove { axis: RelativeAxis::Y, delta: -5 }
+    ]).unwrap();
+
+    {
+        let mut frame = virtual_device.frame();
+        frame.push( InputEventBody::KeyPress( Key::A ) );
+    }
+
+    let mut events = Vec::new();
+    while let Some( event ) = device.read( Some( Duration::from_millis( 100 ) ) ).unwrap() {
+        events.push( event.body );
+    }
+
+    assert_eq!( events, vec![
+        InputEventBody::RelativeMove { axis: RelativeAxis::X, delta: 10 },
+        InputEventBody::RelativeMove { axis: RelativeAxis::Y, delta: -5 },
+        InputEventBody::Flush,
+        InputEventBody::KeyPress( Key::A ),
+        InputEventBody::Flush
+    ]);
+}
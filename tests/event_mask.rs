@@ -0,0 +1,88 @@
+mod common;
+
+use {
+    linux_input::{
+        EventBit,
+        EventKind,
+        InputEventBody,
+        Key,
+        RelativeAxis,
+        VirtualDevice
+    },
+    std::{
+        time::{
+            Duration
+        }
+    }
+};
+
+fn create_virtual_device() -> Option< VirtualDevice > {
+    common::create_virtual_device( "linux-input event mask test", vec![
+        EventBit::Key( Key::A ),
+        EventBit::Key( Key::MouseLeft ),
+        EventBit::RelativeAxis( RelativeAxis::X ),
+        EventBit::RelativeAxis( RelativeAxis::Y )
+    ])
+}
+
+#[test]
+fn test_event_mask_drops_masked_out_events() {
+    let virtual_device = match create_virtual_device() {
+        Some( device ) => device,
+        None => return
+    };
+
+    let device = virtual_device.open_as_device().unwrap();
+    device.set_event_mask( vec![ EventKind::Synchronization, EventKind::Key ] ).unwrap();
+
+    let mut kinds: Vec< _ > = device.event_mask::< EventKind >().unwrap().collect();
+    kinds.sort_by_key( |kind| kind.raw() );
+    assert_eq!( kinds, vec![ EventKind::Synchronization, EventKind::Key ] );
+
+    virtual_device.emit( InputEventBody::RelativeMove { axis: RelativeAxis::X, delta: 10 } ).unwrap();
+    virtual_device.emit( InputEventBody::KeyPress( Key::A ) ).unwrap();
+    virtual_device.emit( InputEventBody::RelativeMove { axis: RelativeAxis::Y, delta: -5 } ).unwrap();
+    virtual_device.emit( InputEventBody::Flush ).unwrap();
+    virtual_device.emit( InputEventBody::KeyRelease( Key::A ) ).unwrap();
+    virtual_device.emit( InputEventBody::RelativeMove { axis: RelativeAxis::X, delta: 1 } ).unwrap();
+    virtual_device.emit( InputEventBody::Flush ).unwrap();
+
+    let mut events = Vec::new();
+    while let Some( event ) = device.read( Some( Duration::from_millis( 100 ) ) ).unwrap() {
+        events.push( event.body );
+    }
+
+    assert_eq!( events, vec![
+        InputEventBody::KeyPress( Key::A ),
+        InputEventBody::Flush,
+        InputEventBody::KeyRelease( Key::A ),
+        InputEventBody::Flush
+    ]);
+}
+
+#[test]
+fn test_event_mask_of_codes() {
+    let virtual_device = match create_virtual_device() {
+        Some( device ) => device,
+        None => return
+    };
+
+    let device = virtual_device.open_as_device().unwrap();
+    device.set_event_mask( vec![ Key::MouseLeft ] ).unwrap();
+    device.set_event_mask( Vec::< RelativeAxis >::new() ).unwrap();
+
+    virtual_device.emit( InputEventBody::KeyPress( Key::A ) ).unwrap();
+    virtual_device.emit( InputEventBody::KeyPress( Key::MouseLeft ) ).unwrap();
+    virtual_device.emit( InputEventBody::RelativeMove { axis: RelativeAxis::X, delta: 10 } ).unwrap();
+    virtual_device.emit( InputEventBody::Flush ).unwrap();
+
+    let mut events = Vec::new();
+    while let Some( event ) = device.read( Some( Duration::from_millis( 100 ) ) ).unwrap() {
+        events.push( event.body );
+    }
+
+    assert_eq!( events, vec![
+        InputEventBody::KeyPress( Key::MouseLeft ),
+        InputEventBody::Flush
+    ]);
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use {
+    linux_input::{
+        AbsoluteAxis,
+        GamepadPreset,
+        VirtualGamepad
+    }
+};
+
+#[test]
+fn test_gamepad_axes_start_at_rest() {
+    let gamepad = match common::skip_without_uinput( VirtualGamepad::create( GamepadPreset::DualShock4 ) ) {
+        Some( gamepad ) => gamepad,
+        None => return
+    };
+
+    let device = gamepad.device().open_as_device().unwrap();
+    assert_eq!( device.abs_info( AbsoluteAxis::X ).unwrap().value, 128 );
+    assert_eq!( device.abs_info( AbsoluteAxis::RY ).unwrap().value, 128 );
+    assert_eq!( device.abs_info( AbsoluteAxis::Z ).unwrap().value, 0 );
+    assert_eq!( device.abs_info( AbsoluteAxis::Hat0X ).unwrap().value, 0 );
+}
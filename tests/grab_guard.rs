@@ -0,0 +1,46 @@
+mod common;
+
+use {
+    linux_input::{
+        ErrorKind,
+        EventBit,
+        GrabGuard,
+        InputEventBody,
+        Key,
+        VirtualDevice
+    },
+    std::{
+        time::{
+            Duration
+        }
+    }
+};
+
+fn create_virtual_device() -> Option< VirtualDevice > {
+    common::create_virtual_device( "linux-input grab guard test", vec![ EventBit::Key( Key::Enter ) ] )
+}
+
+#[test]
+fn test_grab_guard_waits_for_keys_to_be_released() {
+    let virtual_device = match create_virtual_device() {
+        Some( device ) => device,
+        None => return
+    };
+
+    let device = virtual_device.open_as_device().unwrap();
+    virtual_device.emit_frame( &[ InputEventBody::KeyPress( Key::Enter ) ] ).unwrap();
+    assert_eq!( device.pressed_keys().unwrap().collect::< Vec< _ > >(), vec![ Key::Enter ] );
+
+    let error = GrabGuard::after_keys_are_released( &device, Some( Duration::from_millis( 50 ) ) ).err().unwrap();
+    assert_eq!( error.kind(), ErrorKind::TimedOut );
+
+    virtual_device.emit_frame( &[ InputEventBody::KeyRelease( Key::Enter ) ] ).unwrap();
+    {
+        let _guard = GrabGuard::after_keys_are_released( &device, Some( Duration::from_secs( 1 ) ) ).unwrap();
+        let other = virtual_device.open_as_device().unwrap();
+        assert_eq!( other.grab().unwrap_err().kind(), ErrorKind::Busy );
+    }
+
+    let other = virtual_device.open_as_device().unwrap();
+    other.grab().unwrap();
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use {
+    linux_input::{
+        EventBit,
+        InputEventBody,
+        Key,
+        VirtualDevice
+    },
+    std::{
+        time::{
+            Duration
+        }
+    }
+};
+
+fn create_virtual_device() -> Option< VirtualDevice > {
+    common::create_virtual_device( "linux-input stuck keys test", vec![
+        EventBit::Key( Key::A ),
+        EventBit::Key( Key::LeftShift )
+    ])
+}
+
+#[test]
+fn test_release_all() {
+    let virtual_device = match create_virtual_device() {
+        Some( device ) => device,
+        None => return
+    };
+
+    let device = virtual_device.open_as_device().unwrap();
+    virtual_device.emit_frame( &[
+        InputEventBody::KeyPress( Key::LeftShift ),
+        InputEventBody::KeyPress( Key::A )
+    ]).unwrap();
+
+    assert_eq!( virtual_device.pressed_keys(), vec![ Key::A, Key::LeftShift ] );
+    virtual_device.release_all().unwrap();
+    assert_eq!( virtual_device.pressed_keys(), vec![] );
+
+    let mut events = Vec::new();
+    while let Some( event ) = device.read( Some( Duration::from_millis( 100 ) ) ).unwrap() {
+        events.push( event.body );
+    }
+
+    assert_eq!( events, vec![
+        InputEventBody::KeyPress( Key::LeftShift ),
+        InputEventBody::KeyPress( Key::A ),
+        InputEventBody::Flush,
+        InputEventBody::KeyRelease( Key::A ),
+        InputEventBody::KeyRelease( Key::LeftShift ),
+        InputEventBody::Flush
+    ]);
+}